@@ -0,0 +1,72 @@
+//! Compares the batched MultiChannelFft with running one FftwPlan per channel.
+//!
+//! Run with "cargo bench". Uses a plain timing loop rather than the unstable
+//! bench harness so it works on stable Rust.
+
+extern crate rusty_bars;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rusty_bars::fftw::float::FftReal;
+use rusty_bars::fftw::multichannel::MultiChannelFft;
+use rusty_bars::fftw::plan::{FftwBatchPlan, FftwPlan};
+
+
+const FFT_SIZE: usize = 1024;
+const ITERATIONS: u32 = 20000;
+
+
+/// Fill a channel with a deterministic test signal
+fn fill(input: &mut [FftReal], channel: usize) {
+    for (i, value) in input.iter_mut().enumerate() {
+        *value = (((i * 31 + channel * 17) % 101) as FftReal - 50.0) * 100.0;
+    }
+}
+
+
+/// Time ITERATIONS runs of f and return nanoseconds per run
+fn time<F: FnMut()>(mut f: F) -> f64 {
+    // Warm up the caches and let FFTW touch its twiddle factors
+    for _ in 0..100 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    (elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64) / ITERATIONS as f64
+}
+
+
+fn main() {
+    println!("{}-point FFTs, {} iterations", FFT_SIZE, ITERATIONS);
+    println!("{:>8} {:>16} {:>16} {:>8}", "channels", "per-channel ns", "batched ns", "speedup");
+
+    for &channels in [2, 6, 8].iter() {
+        let mut plans: Vec<FftwPlan> = (0..channels).map(|_| FftwPlan::new(FFT_SIZE).unwrap()).collect();
+        for (channel, plan) in plans.iter_mut().enumerate() {
+            fill(plan.get_input_slice(), channel);
+        }
+        let per_channel = time(|| {
+            for plan in plans.iter_mut() {
+                plan.execute();
+            }
+            black_box(plans[0].get_output_slice()[1].re);
+        });
+
+        // Name the backend so this still measures FFTW with "rust-fft" on
+        let mut batched: MultiChannelFft<FftReal, FftwBatchPlan<FftReal>> = MultiChannelFft::new(FFT_SIZE, channels).unwrap();
+        for (channel, input) in batched.get_inputs().into_iter().enumerate() {
+            fill(input, channel);
+        }
+        let batched_time = time(|| {
+            batched.execute();
+            black_box(batched.get_output(0)[1].re);
+        });
+
+        println!("{:>8} {:>16.0} {:>16.0} {:>7.2}x",
+                 channels, per_channel, batched_time, per_channel / batched_time);
+    }
+}
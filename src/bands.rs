@@ -0,0 +1,368 @@
+use std::str::FromStr;
+
+
+/// How the frequency range is divided into bars
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BandScale {
+    /// Equal width bands. Most of the screen goes to the high frequencies.
+    Linear,
+    /// Bands of equal width in log frequency, so each octave gets the same
+    /// space
+    Logarithmic,
+    /// Octave bands centered on 1000Hz * 2^n. The number of bands comes from
+    /// the frequency range, not the screen width.
+    Octave,
+    /// Third octave bands centered on 1000Hz * 2^(n/3), the base 2 series
+    /// of ISO 266. 31 bands cover 20Hz-20kHz.
+    ThirdOctave,
+    /// Bands of equal width in mels, a scale of perceived pitch
+    Mel,
+    /// Bands of equal width in Barks, the ear's critical bands
+    Bark,
+}
+
+
+/// Parses a scale, as given to --bands: "linear", "log", "octave",
+/// "third-octave", "mel" or "bark"
+impl FromStr for BandScale {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<BandScale, String> {
+        match name {
+            "linear" => Ok(BandScale::Linear),
+            "log" => Ok(BandScale::Logarithmic),
+            "octave" => Ok(BandScale::Octave),
+            "third-octave" => Ok(BandScale::ThirdOctave),
+            "mel" => Ok(BandScale::Mel),
+            "bark" => Ok(BandScale::Bark),
+            _ => Err(format!("unknown band scale \"{}\"", name)),
+        }
+    }
+}
+
+
+/// How several values are combined into one band or column
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Aggregation {
+    /// The largest value. A tone keeps its level however many bins are in
+    /// its band.
+    Max,
+    /// The average value
+    Mean,
+    /// The root mean square. For amplitudes, this averages their power.
+    Rms,
+}
+
+
+/// Parses "max", "mean" or "rms"
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Aggregation, String> {
+        match name {
+            "max" => Ok(Aggregation::Max),
+            "mean" => Ok(Aggregation::Mean),
+            "rms" => Ok(Aggregation::Rms),
+            _ => Err(format!("unknown aggregation \"{}\"", name)),
+        }
+    }
+}
+
+
+impl Aggregation {
+    /// Combine (weight, value) pairs, where the weights add up to
+    /// total_weight. Weights are how much of each value falls in the band;
+    /// Max only looks at values with some weight. Returns None if no value
+    /// has any weight, for the caller to interpolate instead.
+    pub(crate) fn combine<I: Iterator<Item=(f64, f64)>>(&self, values: I, total_weight: f64) -> Option<f64> {
+        let mut values = values.filter(|&(weight, _)| weight > 1e-9).peekable();
+        // Nothing with any weight
+        values.peek()?;
+        Some(match *self {
+            Aggregation::Max => values.fold(f64::NEG_INFINITY, |max, (_, value)| max.max(value)),
+            Aggregation::Mean => values.map(|(weight, value)| weight * value).sum::<f64>() / total_weight,
+            Aggregation::Rms => (values.map(|(weight, value)| weight * value * value).sum::<f64>()
+                                 / total_weight).sqrt(),
+        })
+    }
+}
+
+
+/// Which frequencies the bars show and how they are spaced
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BandSettings {
+    pub scale: BandScale,
+    /// How the bins in a band are combined
+    pub aggregation: Aggregation,
+    /// The bottom of the first band in Hz. Must be above 0 for every scale
+    /// but Linear.
+    pub min_freq: f64,
+    /// The top of the last band in Hz. Anything above the Nyquist frequency
+    /// is cut off there.
+    pub max_freq: f64,
+}
+
+
+impl Default for BandSettings {
+    fn default() -> BandSettings {
+        BandSettings {
+            scale: BandScale::Logarithmic,
+            aggregation: Aggregation::Max,
+            min_freq: 20.0,
+            max_freq: 20000.0,
+        }
+    }
+}
+
+
+/// A band of frequencies, in Hz
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Band {
+    pub low: f64,
+    pub center: f64,
+    pub high: f64,
+    /// The FFT bins whose frequencies are in [low, high)
+    first_bin: usize,
+    end_bin: usize,
+}
+
+
+/// Maps FFT bins onto bands. A band's level combines the bins in it, by
+/// default taking the loudest so a tone reads the same level whichever band it
+/// lands in. Bands narrower than a bin, which happens in the bass with log
+/// scales, interpolate between the bins on either side of their center
+/// instead.
+pub struct BandMapper {
+    bands: Vec<Band>,
+    aggregation: Aggregation,
+    /// The frequency step between FFT bins
+    bin_width: f64,
+}
+
+
+impl BandMapper {
+    /// Split the settings' frequency range into band_count bands, for FFT
+    /// output of fft_size/2 bins at the given sample rate. Octave and third
+    /// octave scales ignore band_count.
+    ///
+    /// Panics if the range is empty once clipped to 0 to sample_rate/2, or if
+    /// a non-linear scale starts at 0.
+    pub fn new(settings: BandSettings, band_count: usize, sample_rate: f64, fft_size: usize) -> BandMapper {
+        let nyquist = sample_rate / 2.0;
+        let min = settings.min_freq.max(0.0).min(nyquist);
+        let max = settings.max_freq.min(nyquist);
+        assert!(min < max, "the band range {}-{}Hz is empty", settings.min_freq, settings.max_freq);
+        assert!(min > 0.0 || settings.scale == BandScale::Linear,
+                "the {:?} scale needs a minimum frequency above 0", settings.scale);
+
+        let edges: Vec<(f64, f64, f64)> = match settings.scale {
+            BandScale::Linear => spaced_bands(min, max, band_count, |f| f, |x| x),
+            BandScale::Logarithmic => spaced_bands(min, max, band_count, |f| f.ln(), |x| x.exp()),
+            BandScale::Octave => fractional_octave_bands(min, max, 1.0),
+            BandScale::ThirdOctave => fractional_octave_bands(min, max, 3.0),
+            BandScale::Mel => spaced_bands(min, max, band_count, hz_to_mel, mel_to_hz),
+            BandScale::Bark => spaced_bands(min, max, band_count, hz_to_bark, bark_to_hz),
+        };
+
+        let bin_width = sample_rate / fft_size as f64;
+        let bin_count = fft_size / 2;
+        let bin_at = |freq: f64| ((freq / bin_width).ceil() as usize).min(bin_count);
+        BandMapper {
+            bands: edges.into_iter().map(|(low, center, high)| Band {
+                low: low,
+                center: center,
+                high: high,
+                first_bin: bin_at(low),
+                end_bin: bin_at(high),
+            }).collect(),
+            aggregation: settings.aggregation,
+            bin_width: bin_width,
+        }
+    }
+
+    /// The bands, from lowest to highest
+    pub fn get_bands(&self) -> &[Band] {
+        &self.bands[..]
+    }
+
+    /// Compute the level of each band from the FFT output. Mean and RMS only
+    /// make sense for amplitudes, not decibels.
+    pub fn map(&self, input: &[f64]) -> Vec<f64> {
+        let mut output = Vec::with_capacity(self.bands.len());
+        self.map_into(input, &mut output);
+        output
+    }
+
+    /// Like map, but adds the band levels to the end of output, so a buffer
+    /// can be reused from frame to frame
+    pub fn map_into(&self, input: &[f64], output: &mut Vec<f64>) {
+        output.extend(self.bands.iter().map(|band| {
+            let first = band.first_bin.min(input.len());
+            let end = band.end_bin.min(input.len());
+            let combined = if first < end {
+                self.aggregation.combine(input[first..end].iter().map(|&value| (1.0, value)),
+                                         (end - first) as f64)
+            } else {
+                None
+            };
+            combined.unwrap_or_else(|| interpolate(input, band.center / self.bin_width))
+        }));
+    }
+}
+
+
+/// Linearly interpolate between the input values around a fractional index
+pub(crate) fn interpolate(input: &[f64], position: f64) -> f64 {
+    if input.is_empty() {
+        return 0.0;
+    }
+    let last = input.len() - 1;
+    let index = (position.floor() as usize).min(last);
+    let fraction = position - index as f64;
+    let next = (index + 1).min(last);
+    input[index] * (1.0 - fraction.min(1.0)) + input[next] * fraction.min(1.0)
+}
+
+
+/// count bands from min to max of equal width after warping frequencies with
+/// to_scale. from_scale is its inverse. Returns (low, center, high) for each
+/// band, where the center is halfway between the edges on the warped scale.
+fn spaced_bands<F, G>(min: f64, max: f64, count: usize, to_scale: F, from_scale: G) -> Vec<(f64, f64, f64)>
+    where F: Fn(f64) -> f64, G: Fn(f64) -> f64 {
+    let (bottom, top) = (to_scale(min), to_scale(max));
+    let step = (top - bottom) / count as f64;
+    (0..count).map(|i| {
+        let low = bottom + step * i as f64;
+        (from_scale(low), from_scale(low + step / 2.0), from_scale(low + step))
+    }).collect()
+}
+
+
+/// 1/fraction octave bands with centers of 1000Hz * 2^(n/fraction) from min
+/// to max. The edges are half a band either side of the center. Centers up to
+/// a quarter band outside the range count, so that a range like 20Hz-20kHz
+/// includes the bands nominally centered on 20Hz and 20kHz.
+fn fractional_octave_bands(min: f64, max: f64, fraction: f64) -> Vec<(f64, f64, f64)> {
+    let first = (fraction * (min / 1000.0).log2() - 0.25).ceil() as i32;
+    let last = (fraction * (max / 1000.0).log2() + 0.25).floor() as i32;
+    let half_band = 2f64.powf(0.5 / fraction);
+    (first..last + 1).map(|n| {
+        let center = 1000.0 * 2f64.powf(n as f64 / fraction);
+        (center / half_band, center, center * half_band)
+    }).collect()
+}
+
+
+/// O'Shaughnessy's mel scale, as used for MFCCs
+fn hz_to_mel(freq: f64) -> f64 {
+    2595.0 * (1.0 + freq / 700.0).log10()
+}
+
+
+fn mel_to_hz(mel: f64) -> f64 {
+    700.0 * (10f64.powf(mel / 2595.0) - 1.0)
+}
+
+
+/// Traunmüller's approximation of the Bark scale
+fn hz_to_bark(freq: f64) -> f64 {
+    26.81 * freq / (1960.0 + freq) - 0.53
+}
+
+
+fn bark_to_hz(bark: f64) -> f64 {
+    1960.0 * (bark + 0.53) / (26.28 - bark)
+}
+
+
+#[test]
+fn test_spaced_scales() {
+    let settings = BandSettings { scale: BandScale::Logarithmic, ..BandSettings::default() };
+    for &scale in [BandScale::Linear, BandScale::Logarithmic, BandScale::Mel, BandScale::Bark].iter() {
+        let mapper = BandMapper::new(BandSettings { scale: scale, ..settings }, 40, 48000.0, 4096);
+        let bands = mapper.get_bands();
+        assert_eq!(bands.len(), 40);
+        assert!((bands[0].low - 20.0).abs() < 1e-6, "{:?} starts at {}", scale, bands[0].low);
+        assert!((bands[39].high - 20000.0).abs() < 1e-6, "{:?} ends at {}", scale, bands[39].high);
+        for pair in bands.windows(2) {
+            assert!((pair[0].high - pair[1].low).abs() < 1e-6);
+            assert!(pair[0].low < pair[0].center && pair[0].center < pair[0].high);
+        }
+    }
+
+    // Log bands are the same ratio wide: 3 decades in 30 bands
+    let mapper = BandMapper::new(settings, 30, 48000.0, 4096);
+    for band in mapper.get_bands().iter() {
+        assert!((band.high / band.low - 10f64.powf(0.1)).abs() < 1e-9);
+    }
+}
+
+
+#[test]
+fn test_octave_bands() {
+    let settings = BandSettings { scale: BandScale::Octave, ..BandSettings::default() };
+    let centers: Vec<f64> = BandMapper::new(settings, 0, 44100.0, 1024).get_bands().iter()
+        .map(|band| band.center).collect();
+    assert_eq!(centers, vec![31.25, 62.5, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0]);
+
+    let settings = BandSettings { scale: BandScale::ThirdOctave, ..settings };
+    let mapper = BandMapper::new(settings, 0, 48000.0, 4096);
+    let bands = mapper.get_bands();
+    assert_eq!(bands.len(), 31);
+    let band_1k = bands.iter().find(|band| band.center == 1000.0).unwrap();
+    assert!((band_1k.low - 890.9).abs() < 0.1 && (band_1k.high - 1122.5).abs() < 0.1);
+
+    // Cut off at the Nyquist frequency
+    let mapper = BandMapper::new(settings, 0, 22050.0, 4096);
+    assert!(mapper.get_bands().iter().all(|band| band.center <= 11025.0));
+}
+
+
+#[test]
+fn test_map_bins_to_bands() {
+    // 1024 bins 23.4Hz apart, silent but for 1kHz (bin 43)
+    let (rate, size) = (48000.0, 2048);
+    let mut input = vec![-120.0; size / 2];
+    input[43] = -6.0;
+    for &scale in [BandScale::Linear, BandScale::Logarithmic, BandScale::ThirdOctave].iter() {
+        let settings = BandSettings { scale: scale, ..BandSettings::default() };
+        let mapper = BandMapper::new(settings, 64, rate, size);
+        let levels = mapper.map(&input);
+        let loud: Vec<&Band> = mapper.get_bands().iter().zip(levels.iter())
+            .filter(|&(_, &level)| level == -6.0).map(|(band, _)| band).collect();
+        assert_eq!(loud.len(), 1, "{:?}", scale);
+        let freq = 43.0 * rate / size as f64;
+        assert!(loud[0].low <= freq && freq < loud[0].high);
+    }
+
+    // Bass bands narrower than a bin are interpolated rather than left empty
+    input[1] = -20.0;
+    input[2] = -40.0;
+    let settings = BandSettings { scale: BandScale::Logarithmic, ..BandSettings::default() };
+    let levels = BandMapper::new(settings, 200, rate, size).map(&input);
+    assert!(levels[..10].iter().all(|&level| level > -120.0));
+}
+
+
+#[test]
+fn test_band_aggregation() {
+    // A linear band exactly 4 bins wide: bins 4-7 of 8 at 16Hz
+    let input = [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 3.0, 3.0];
+    let settings = BandSettings { scale: BandScale::Linear, min_freq: 0.0, max_freq: 8.0, ..BandSettings::default() };
+    for &(aggregation, expected) in [(Aggregation::Max, 3.0), (Aggregation::Mean, 2.0),
+                                     (Aggregation::Rms, 5f64.sqrt())].iter() {
+        let mapper = BandMapper::new(BandSettings { aggregation: aggregation, ..settings }, 2, 16.0, 16);
+        assert_eq!(mapper.map(&input), vec![0.0, expected]);
+    }
+}
+
+
+#[test]
+fn test_combine_without_weight() {
+    // Nothing to combine leaves it to the caller, rather than -inf or NaN
+    for &aggregation in [Aggregation::Max, Aggregation::Mean, Aggregation::Rms].iter() {
+        assert_eq!(aggregation.combine(vec![(1e-12, 5.0), (0.0, 7.0)].into_iter(), 1e-12), None);
+        assert_eq!(aggregation.combine(Vec::new().into_iter(), 0.0), None);
+    }
+    assert_eq!(Aggregation::Max.combine(vec![(1e-12, 9.0), (0.5, 2.0)].into_iter(), 0.5), Some(2.0));
+}
@@ -1,90 +1,236 @@
 use std::slice;
+use std::str::FromStr;
 //use std::num::Float;
+use fftw::backend::{DefaultBackend, FftBackend, FftError};
 use fftw::multichannel::MultiChannelFft;
-use fftw::hanning::HanningWindowCalculator;
+use fftw::window::{WindowCalculator, WindowFunction};
+use fftw::float::{FftFloat, FftReal};
+use fftw::types::{FftwComplex, PlannerFlags};
 
 
-/// Audio FFT for 16bit little endian audio data (S16LE)
-pub struct AudioFft {
+/// The magnitude of a full scale S16 sample. A sine peaking here reads 0dBFS.
+pub const FULL_SCALE: f64 = 32768.0;
+
+
+/// The default for AudioFft::set_floor, in dBFS. Quieter bins, including
+/// silent ones, read as this.
+pub const DEFAULT_FLOOR: f64 = -120.0;
+
+
+/// How compute_output combines the channels into get_output
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChannelMix {
+    /// The loudest channel in each bin
+    Max,
+    /// The total power of every channel. Two channels with the same level
+    /// read 3dB louder than either.
+    Sum,
+    /// The average power of the channels
+    Average,
+}
+
+
+/// Parses "max", "sum" or "average"
+impl FromStr for ChannelMix {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<ChannelMix, String> {
+        match name {
+            "max" => Ok(ChannelMix::Max),
+            "sum" => Ok(ChannelMix::Sum),
+            "average" => Ok(ChannelMix::Average),
+            _ => Err(format!("unknown channel mix \"{}\"", name)),
+        }
+    }
+}
+
+
+/// Audio FFT for 16bit little endian audio data (S16LE). B is the FFT
+/// implementation; see fftw::backend.
+///
+/// Samples go into a ring buffer holding the last fft_size frames, so
+/// consecutive FFTs can overlap. A new FFT is ready every hop_size frames once
+/// the buffer has filled; with a hop of half the FFT size, frames come twice as
+/// often as they would without overlap.
+pub struct AudioFft<T: FftFloat = FftReal, B: FftBackend<T> = DefaultBackend<T>> {
     /// The multichannel fft object that does the work for us
-    multichan_fft: MultiChannelFft,
-    /// The input cursor indicates how much data has been read in. Input is
-    /// 16bit integers, inerleaved by channels. The so that means the maximum
-    /// value of input_cursor is channel_count * fft_size
-    input_cursor: usize,
-    /// The number of elements needed to fill an FFT. This is equal to the size
-    /// of the FFT times the number of channels
-    required_input: usize,
+    multichan_fft: MultiChannelFft<T, B>,
+    /// The last fft_size samples of every channel, one channel after another.
+    /// Each channel's oldest sample is at write_pos once the buffer is full.
+    ring: Vec<T>,
+    /// Where the next frame goes in each channel's part of the ring
+    write_pos: usize,
+    /// The channel the next sample is for. Input is 16bit integers,
+    /// interleaved by channels.
+    channel_cursor: usize,
+    /// How many frames are in the ring, up to fft_size
+    filled: usize,
+    /// Frames read since the last execute
+    since_execute: usize,
+    /// Frames between FFTs
+    hop_size: usize,
     /// The size of the FFT
     fft_size: usize,
     /// The number of audio channels. Ex: 2 for stereo audio.
     channel_count: usize,
-    /// Helper for executing the Hanning window function as data is inserted
-    hanning: HanningWindowCalculator,
+    /// Helper for applying the window function as the ring is unrolled
+    window: WindowCalculator<T>,
+    /// The lowest reading compute_output gives, in dBFS
+    floor: f64,
+    /// How the channels are combined into output
+    channel_mix: ChannelMix,
     /// Holds output for the combined channels
-    output: Vec<f64>
+    output: Vec<T>,
+    /// The levels of each channel, one channel after another
+    channel_outputs: Vec<T>,
+    /// The levels of (L+R)/2 and (L-R)/2 for stereo input. Empty for any
+    /// other number of channels.
+    mid_output: Vec<T>,
+    side_output: Vec<T>,
 }
 
 
-impl AudioFft {
-    /// Create a new AudioFft
-    pub fn new(fft_size: usize, channel_count: usize) -> AudioFft {
+impl<T: FftFloat, B: FftBackend<T>> AudioFft<T, B> {
+    /// Create a new AudioFft. Fails if the FFT backend can't handle fft_size;
+    /// see FftBackend::new.
+    pub fn new(fft_size: usize, channel_count: usize) -> Result<AudioFft<T, B>, FftError> {
+        AudioFft::with_flags(fft_size, channel_count, PlannerFlags::Measure)
+    }
+
+    /// Create a new AudioFft, planning with the given effort. The hop size
+    /// starts out as fft_size, so the FFTs don't overlap.
+    pub fn with_flags(fft_size: usize, channel_count: usize, flags: PlannerFlags) -> Result<AudioFft<T, B>, FftError> {
         let mut out_vec = Vec::with_capacity(fft_size/2);
         for _ in 0..fft_size/2 {
-            out_vec.push(0.0);
+            out_vec.push(T::from_f64(0.0));
         }
-        AudioFft {
-            multichan_fft: MultiChannelFft::new(fft_size, channel_count),
-            input_cursor: 0,
+        Ok(AudioFft {
+            multichan_fft: MultiChannelFft::with_flags(fft_size, channel_count, flags)?,
+            ring: vec![T::from_f64(0.0); fft_size * channel_count],
+            write_pos: 0,
+            channel_cursor: 0,
+            filled: 0,
+            since_execute: 0,
+            hop_size: fft_size,
             fft_size: fft_size,
             channel_count: channel_count,
-            required_input: channel_count * fft_size,
-            hanning: HanningWindowCalculator::new(fft_size),
+            window: WindowCalculator::new(WindowFunction::Hann, fft_size),
+            floor: DEFAULT_FLOOR,
+            channel_mix: ChannelMix::Max,
             output: out_vec,
-        }
+            channel_outputs: vec![T::from_f64(DEFAULT_FLOOR); fft_size/2 * channel_count],
+            mid_output: vec![T::from_f64(DEFAULT_FLOOR); if channel_count == 2 { fft_size/2 } else { 0 }],
+            side_output: vec![T::from_f64(DEFAULT_FLOOR); if channel_count == 2 { fft_size/2 } else { 0 }],
+        })
+    }
+
+    /// Set the number of frames between FFTs. A hop of fft_size/2 is 50%
+    /// overlap, fft_size/4 is 75%. Panics if hop_size is 0.
+    pub fn set_hop_size(&mut self, hop_size: usize) {
+        assert!(hop_size > 0, "the hop size must be at least 1");
+        self.hop_size = hop_size;
+    }
+
+    /// The number of frames between FFTs
+    pub fn get_hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Use a different window function. The default is Hann.
+    pub fn set_window(&mut self, window: WindowFunction) {
+        self.window = WindowCalculator::new(window, self.fft_size);
+    }
+
+    /// The window applied before each FFT, with its coherent gain and
+    /// equivalent noise bandwidth for correcting amplitudes
+    pub fn get_window(&self) -> &WindowCalculator<T> {
+        &self.window
+    }
+
+    /// Set the lowest reading compute_output gives, in dBFS. The default is
+    /// DEFAULT_FLOOR.
+    pub fn set_floor(&mut self, floor: f64) {
+        self.floor = floor;
+    }
+
+    /// The lowest reading compute_output gives, in dBFS
+    pub fn get_floor(&self) -> f64 {
+        self.floor
+    }
+
+    /// Set how the channels are combined into get_output. The default is
+    /// ChannelMix::Max.
+    pub fn set_channel_mix(&mut self, channel_mix: ChannelMix) {
+        self.channel_mix = channel_mix;
+    }
+
+    /// How the channels are combined into get_output
+    pub fn get_channel_mix(&self) -> ChannelMix {
+        self.channel_mix
     }
 
+    /// Whether enough new data has been fed in to run the next FFT
+    pub fn is_ready(&self) -> bool {
+        self.filled == self.fft_size && self.since_execute >= self.hop_size
+    }
 
-    /// Exeuce the FFT
+    /// Exeuce the FFT on the last fft_size frames
     pub fn execute(&mut self) {
+        {
+            let mut inputs = self.multichan_fft.get_inputs();
+            for (channel, input) in inputs.iter_mut().enumerate() {
+                let ring = &self.ring[channel * self.fft_size..(channel + 1) * self.fft_size];
+                // Unroll the ring, oldest sample first, applying the window
+                let (newer, older) = ring.split_at(self.write_pos);
+                for (index, &value) in older.iter().chain(newer.iter()).enumerate() {
+                    input[index] = self.window.get_value(index, value);
+                }
+            }
+        }
         self.multichan_fft.execute();
-        self.input_cursor = 0;
+        self.since_execute = 0;
     }
 
     /// Allows a client to feed data into the FFT in chunks. This is useful for
     /// ineracting with PulseAudio because its asynchronous API gives audio data
     /// in arbitrary chunk sizes depending on how much data is available.
     ///
+    /// Reading stops as soon as the next FFT is ready, so no data is lost
+    /// between frames. When is_ready returns true, call execute and then feed
+    /// in the rest of the input.
+    ///
     /// Arguments:
     ///     input: A slice pointing at S16LE audio data for the number of
     ///            channels in self.channel_count
     /// Returns:
-    ///     The number of bytes it read. If the number of bytes returned is
-    ///     less than the input size, the FFT is ready to execute.
+    ///     The number of samples it read. If it is less than the input size,
+    ///     the FFT is ready to execute; is_ready also says so when the FFT
+    ///     became ready on the last sample.
     pub fn feed_data(&mut self, input: &[i16]) -> usize {
-        let mut bytes_read: usize = 0;
-
-        let mut inputs = self.multichan_fft.get_inputs();
+        let mut samples_read: usize = 0;
 
         for value in input.iter() {
-            // If there is enough data to run the FFT, return the number of
-            // bytes that were read out of this input slice
-            if self.input_cursor == self.required_input {
-                return bytes_read;
+            if self.is_ready() {
+                break;
             }
 
-            // The channel number and index the current value is for
-            let channel_num = self.input_cursor % self.channel_count;
-            let channel_index = self.input_cursor / self.channel_count;
-
-            // Compute the hanning window value for the element set the input
-            inputs[channel_num][channel_index] = self.hanning.get_value(channel_index, *value as f64);
+            let index = self.channel_cursor * self.fft_size + self.write_pos;
+            self.ring[index] = T::from_f64(*value as f64);
+            samples_read += 1;
 
-            bytes_read += 1;
-            self.input_cursor += 1;
+            // Move on to the next frame once every channel has a sample
+            self.channel_cursor += 1;
+            if self.channel_cursor == self.channel_count {
+                self.channel_cursor = 0;
+                self.write_pos = (self.write_pos + 1) % self.fft_size;
+                if self.filled < self.fft_size {
+                    self.filled += 1;
+                }
+                self.since_execute += 1;
+            }
         }
 
-        bytes_read
+        samples_read
     }
 
     /// Wrapper for feed_data which takes an &[u8] slice instead of an &[i16]
@@ -95,27 +241,313 @@ impl AudioFft {
         self.feed_data(unsafe{ slice::from_raw_parts(i16_ptr, input.len()/2) }) * 2
     }
 
-    /// Computes the combined output of all channels into the output field of
-    /// this struct. Every time compute_output is called, it reuses the same
-    /// output vector to avoid allocations.
+    /// Copy the latest output.len() samples of a channel, oldest first, as
+    /// read from the input. Samples from before the first input are 0. At
+    /// most the last fft_size samples are kept.
+    pub fn copy_samples(&self, channel: usize, output: &mut [f64]) {
+        assert!(output.len() <= self.fft_size, "only the last fft_size samples are kept");
+        let ring = &self.ring[channel * self.fft_size..(channel + 1) * self.fft_size];
+        let start = self.write_pos + self.fft_size - output.len();
+        for (index, value) in output.iter_mut().enumerate() {
+            *value = ring[(start + index) % self.fft_size].to_f64();
+        }
+    }
+
+    /// Computes the levels of each channel, their combination and, for stereo
+    /// input, the mid and side signals. Every time compute_output is called,
+    /// it reuses the same output vectors to avoid allocations.
+    ///
+    /// Levels are in dBFS. The FFT output is normalized for the FFT size, the
+    /// window's coherent gain and the S16 full scale, so a full scale sine
+    /// centered on a bin reads 0dB. Readings never go below the floor; see
+    /// set_floor.
     pub fn compute_output(&mut self) {
-        let mut first = true;
-        for channel in self.multichan_fft.channel_plans.iter() {
-            for (index, &value) in channel.get_output_slice()[0..self.fft_size/2].iter().enumerate() {
-                // Turn the FFT output value into decibals
-                let power: f64 = 20.0 * value.abs().log10();
-                // If it's bigger than the biggest value for this channel for
-                // this execution, then replace the current value
-                if first || power > self.output[index] {
-                    self.output[index] = power;
+        // A sine of amplitude A comes out of the FFT with a magnitude of
+        // A * fft_size * coherent_gain / 2. The other half of its energy is in
+        // the negative frequencies, which a real FFT leaves off, except at DC.
+        let full_scale = self.fft_size as f64 * self.window.get_coherent_gain() * FULL_SCALE;
+        let dc_scale = T::from_f64(1.0 / full_scale);
+        let scale = T::from_f64(2.0 / full_scale);
+        let floor = T::from_f64(self.floor);
+        let half = self.fft_size / 2;
+
+        for (index, level) in self.output.iter_mut().enumerate() {
+            let bin_scale = if index == 0 { dc_scale } else { scale };
+            let mut max = T::from_f64(0.0);
+            let mut power = T::from_f64(0.0);
+            for channel in 0..self.channel_count {
+                let amplitude = self.multichan_fft.get_output(channel)[index].abs() * bin_scale;
+                self.channel_outputs[channel * half + index] = decibels(amplitude, floor);
+                if amplitude > max {
+                    max = amplitude;
                 }
+                power = power + amplitude * amplitude;
             }
-            first = false;
+            let channel_count = T::from_f64(self.channel_count as f64);
+            *level = match self.channel_mix {
+                ChannelMix::Max => decibels(max, floor),
+                ChannelMix::Sum => decibels(power.sqrt(), floor),
+                ChannelMix::Average => decibels((power / channel_count).sqrt(), floor),
+            };
         }
+
+        if self.channel_count == 2 {
+            let one_half = T::from_f64(0.5);
+            for index in 0..half {
+                let bin_scale = if index == 0 { dc_scale } else { scale };
+                let left = self.multichan_fft.get_output(0)[index];
+                let right = self.multichan_fft.get_output(1)[index];
+                let mid = FftwComplex{re: (left.re + right.re) * one_half, im: (left.im + right.im) * one_half};
+                let side = FftwComplex{re: (left.re - right.re) * one_half, im: (left.im - right.im) * one_half};
+                self.mid_output[index] = decibels(mid.abs() * bin_scale, floor);
+                self.side_output[index] = decibels(side.abs() * bin_scale, floor);
+            }
+        }
+    }
+
+    /// The number of audio channels
+    pub fn get_channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    /// The size of the FFT
+    pub fn get_fft_size(&self) -> usize {
+        self.fft_size
     }
 
     /// Borrow the combined output vector
-    pub fn get_output(&self) -> &[f64] {
+    pub fn get_output(&self) -> &[T] {
         &self.output[..]
     }
+
+    /// Borrow the levels of a single channel. Ex: 0 for left and 1 for right.
+    pub fn get_channel_output(&self, channel: usize) -> &[T] {
+        let half = self.fft_size / 2;
+        &self.channel_outputs[channel * half..(channel + 1) * half]
+    }
+
+    /// Borrow the levels of the mid signal, (L+R)/2: what the channels have in
+    /// common. A mono signal reads the same here as in either channel. None
+    /// unless the input is stereo.
+    pub fn get_mid_output(&self) -> Option<&[T]> {
+        if self.channel_count == 2 { Some(&self.mid_output[..]) } else { None }
+    }
+
+    /// Borrow the levels of the side signal, (L-R)/2: the difference between
+    /// the channels, which is silent for mono and louder the wider the stereo
+    /// image. None unless the input is stereo.
+    pub fn get_side_output(&self) -> Option<&[T]> {
+        if self.channel_count == 2 { Some(&self.side_output[..]) } else { None }
+    }
+}
+
+
+/// Converts an amplitude relative to full scale to decibels. Silence is
+/// log10(0), which is -inf, so this clamps to the floor.
+fn decibels<T: FftFloat>(amplitude: T, floor: T) -> T {
+    let level = T::from_f64(20.0) * amplitude.log10();
+    if level > floor { level } else { floor }
+}
+
+
+/// Run one FFT of a sine with the given peak amplitude and frequency in
+/// cycles per FFT, and return the levels
+#[cfg(test)]
+fn measure_sine(window: WindowFunction, amplitude: f64, cycles: f64) -> Vec<f64> {
+    use std::f64::consts::PI;
+    let size = 1024;
+    let input: Vec<i16> = (0..size)
+        .map(|i| (amplitude * (2.0 * PI * cycles * i as f64 / size as f64).sin()).round() as i16)
+        .collect();
+    let mut fft: AudioFft<f64> = AudioFft::new(size, 1).unwrap();
+    fft.set_window(window);
+    assert_eq!(fft.feed_data(&input), size);
+    assert!(fft.is_ready());
+    fft.execute();
+    fft.compute_output();
+    fft.get_output().to_vec()
+}
+
+
+/// Run one FFT of a stereo sine 64 cycles long with the given amplitude in
+/// each channel. A negative amplitude inverts the channel.
+#[cfg(test)]
+fn stereo_sine(left: f64, right: f64, channel_mix: ChannelMix) -> AudioFft<f64> {
+    use std::f64::consts::PI;
+    let size = 1024;
+    let mut input: Vec<i16> = Vec::with_capacity(size * 2);
+    for i in 0..size {
+        let sine = (2.0 * PI * 64.0 * i as f64 / size as f64).sin();
+        input.push((left * sine).round() as i16);
+        input.push((right * sine).round() as i16);
+    }
+    let mut fft: AudioFft<f64> = AudioFft::new(size, 2).unwrap();
+    fft.set_channel_mix(channel_mix);
+    fft.feed_data(&input);
+    fft.execute();
+    fft.compute_output();
+    fft
+}
+
+
+/// Feed samples through an AudioFft and count the frames that come out
+#[cfg(test)]
+fn count_frames<T: FftFloat, B: FftBackend<T>>(fft: &mut AudioFft<T, B>, input: &[i16]) -> usize {
+    let mut frames = 0;
+    let mut fed = 0;
+    loop {
+        fed += fft.feed_data(&input[fed..]);
+        if !fft.is_ready() {
+            break;
+        }
+        fft.execute();
+        frames += 1;
+    }
+    assert_eq!(fed, input.len());
+    frames
+}
+
+
+#[test]
+fn test_hop_size_overlaps_frames() {
+    // 4096 stereo frames
+    let input: Vec<i16> = (0..4096 * 2).map(|i| (i % 200) as i16).collect();
+
+    // Without overlap, one frame per 1024
+    let mut fft: AudioFft<f64> = AudioFft::new(1024, 2).unwrap();
+    assert_eq!(count_frames(&mut fft, &input), 4);
+
+    // With 75% overlap, the first frame after 1024 and then every 256
+    let mut fft: AudioFft<f64> = AudioFft::new(1024, 2).unwrap();
+    fft.set_hop_size(256);
+    assert_eq!(count_frames(&mut fft, &input), 13);
+
+    // The same data in small, odd sized chunks gives the same frames
+    let mut fft: AudioFft<f64> = AudioFft::new(1024, 2).unwrap();
+    fft.set_hop_size(256);
+    let frames: usize = input.chunks(333).map(|chunk| count_frames(&mut fft, chunk)).sum();
+    assert_eq!(frames, 13);
+}
+
+
+#[test]
+fn test_overlapping_frames_see_the_same_signal() {
+    // A steady tone should give the same spectrum in every frame, which only
+    // happens if the ring buffer is unrolled in order
+    let input: Vec<i16> = (0..4096).map(|i| ((i as f64 * 0.3).sin() * 10000.0) as i16).collect();
+    let mut fft: AudioFft<f64> = AudioFft::new(256, 1).unwrap();
+    fft.set_hop_size(100);
+
+    let mut spectra: Vec<Vec<f64>> = Vec::new();
+    let mut fed = 0;
+    loop {
+        fed += fft.feed_data(&input[fed..]);
+        if !fft.is_ready() {
+            break;
+        }
+        fft.execute();
+        fft.compute_output();
+        spectra.push(fft.get_output().to_vec());
+    }
+
+    assert!(spectra.len() > 10);
+    let peak = |spectrum: &Vec<f64>| {
+        (0..spectrum.len()).fold(0, |best, i| if spectrum[i] > spectrum[best] { i } else { best })
+    };
+    for spectrum in spectra.iter() {
+        assert_eq!(peak(spectrum), peak(&spectra[0]));
+    }
+}
+
+
+#[test]
+fn test_full_scale_sine_reads_0_dbfs() {
+    for &window in [WindowFunction::Rectangular, WindowFunction::Hann, WindowFunction::Hamming,
+                    WindowFunction::BlackmanHarris, WindowFunction::FlatTop,
+                    WindowFunction::Kaiser(8.6)].iter() {
+        let levels = measure_sine(window, 32767.0, 64.0);
+        assert!(levels[64].abs() < 0.01, "{:?} read {}dB", window, levels[64]);
+        assert!(levels.iter().all(|&level| level <= levels[64]));
+    }
+}
+
+
+#[test]
+fn test_sine_levels() {
+    // Each halving of the amplitude is 6.02dB
+    for &(amplitude, expected) in [(16384.0, -6.02), (3277.0, -20.0), (328.0, -40.0)].iter() {
+        let level = measure_sine(WindowFunction::Hann, amplitude, 100.0)[100];
+        assert!((level - expected).abs() < 0.05, "{} read {}dB", amplitude, level);
+    }
+
+    // A flat-top window reads the level right even halfway between bins
+    let level = measure_sine(WindowFunction::FlatTop, 16384.0, 100.5)[100];
+    assert!((level + 6.02).abs() < 0.05, "read {}dB", level);
+}
+
+
+#[test]
+fn test_output_floor() {
+    // Silence reads the floor, not -inf
+    let levels = measure_sine(WindowFunction::Hann, 0.0, 10.0);
+    assert!(levels.iter().all(|&level| level == DEFAULT_FLOOR));
+
+    let mut fft: AudioFft<f64> = AudioFft::new(256, 2).unwrap();
+    fft.set_floor(-60.0);
+    let quiet: Vec<i16> = (0..512).map(|i| if i % 4 == 0 { 1 } else { 0 }).collect();
+    fft.feed_data(&quiet);
+    fft.execute();
+    fft.compute_output();
+    assert!(fft.get_output().iter().all(|&level| level == -60.0));
+}
+
+
+#[test]
+fn test_channel_outputs() {
+    let fft = stereo_sine(32767.0, 3277.0, ChannelMix::Max);
+    assert!(fft.get_channel_output(0)[64].abs() < 0.01);
+    assert!((fft.get_channel_output(1)[64] + 20.0).abs() < 0.05);
+    assert_eq!(fft.get_output()[64], fft.get_channel_output(0)[64]);
+
+    // Two channels at the same level have twice the power of either
+    let fft = stereo_sine(16384.0, 16384.0, ChannelMix::Sum);
+    assert!((fft.get_output()[64] - fft.get_channel_output(0)[64] - 3.01).abs() < 0.01);
+    let fft = stereo_sine(16384.0, 16384.0, ChannelMix::Average);
+    assert!((fft.get_output()[64] - fft.get_channel_output(0)[64]).abs() < 0.01);
+}
+
+
+#[test]
+fn test_mid_side() {
+    // Mono is all mid
+    let fft = stereo_sine(16384.0, 16384.0, ChannelMix::Max);
+    assert!((fft.get_mid_output().unwrap()[64] + 6.02).abs() < 0.05);
+    assert_eq!(fft.get_side_output().unwrap()[64], DEFAULT_FLOOR);
+
+    // Out of phase is all side
+    let fft = stereo_sine(16384.0, -16384.0, ChannelMix::Max);
+    assert_eq!(fft.get_mid_output().unwrap()[64], DEFAULT_FLOOR);
+    assert!((fft.get_side_output().unwrap()[64] + 6.02).abs() < 0.05);
+
+    // Only stereo has mid and side
+    let fft: AudioFft<f64> = AudioFft::new(256, 1).unwrap();
+    assert!(fft.get_mid_output().is_none() && fft.get_side_output().is_none());
+}
+
+
+#[test]
+fn test_copy_samples() {
+    let mut fft: AudioFft<f64> = AudioFft::new(8, 2).unwrap();
+    let mut left = [0.0; 4];
+    fft.copy_samples(0, &mut left);
+    assert_eq!(left, [0.0; 4]);
+
+    // Ten frames wrap around the ring
+    let input: Vec<i16> = (1..11).flat_map(|i| vec![i, -i]).collect();
+    assert_eq!(count_frames(&mut fft, &input), 1);
+    let mut right = [0.0; 8];
+    fft.copy_samples(0, &mut left);
+    fft.copy_samples(1, &mut right);
+    assert_eq!(left, [7.0, 8.0, 9.0, 10.0]);
+    assert_eq!(right, [-3.0, -4.0, -5.0, -6.0, -7.0, -8.0, -9.0, -10.0]);
 }
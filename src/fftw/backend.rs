@@ -0,0 +1,63 @@
+use fftw::float::FftFloat;
+use fftw::types::{FftwComplex, PlannerFlags};
+#[cfg(all(feature = "fftw", not(feature = "rust-fft")))]
+use fftw::plan::FftwBatchPlan;
+#[cfg(any(feature = "rust-fft", not(feature = "fftw")))]
+use fftw::rust_fft::RustFft;
+
+
+/// Errors from creating an FFT
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FftError {
+    /// The backend can't transform this many points. FFTW handles any size
+    /// but 0; RustFft only handles powers of two.
+    UnsupportedSize(usize),
+    /// FFTW couldn't make a plan. This happens with PlannerFlags::WisdomOnly
+    /// when there's no wisdom for the size.
+    PlanFailed,
+}
+
+
+/// A real-to-complex FFT implementation. MultiChannelFft and AudioFft are
+/// written against this so the FFT library can be swapped out: FFTW
+/// (FftwBatchPlan) or the pure Rust RustFft.
+///
+/// A backend transforms count real signals of size points at once. The inputs
+/// are stored one after another in get_input_slice, size values each, and the
+/// outputs in get_output_slice, size/2+1 values each. Like FFTW, the output is
+/// not normalized.
+pub trait FftBackend<T: FftFloat> {
+    /// Plan the FFTs. flags and threads are hints which backends without a
+    /// planner or thread support ignore. Fails if the backend can't handle
+    /// the size, or can't plan it with the flags given.
+    fn new(size: usize, count: usize, flags: PlannerFlags, threads: usize) -> Result<Self, FftError>
+        where Self: Sized;
+
+    /// Transform every input into its output
+    fn execute(&mut self);
+
+    /// All of the inputs
+    fn get_input_slice<'a>(&'a mut self) -> &'a mut [T];
+
+    /// All of the outputs
+    fn get_output_slice<'a>(&'a self) -> &'a [FftwComplex<T>];
+
+    /// The number of threads execute runs on
+    fn get_thread_count(&self) -> usize {
+        1
+    }
+}
+
+
+/// The backend used unless one is picked explicitly: FFTW, or RustFft with the
+/// "rust-fft" feature or when FFTW isn't linked.
+#[cfg(all(feature = "fftw", not(feature = "rust-fft")))]
+pub type DefaultBackend<T> = FftwBatchPlan<T>;
+#[cfg(any(feature = "rust-fft", not(feature = "fftw")))]
+pub type DefaultBackend<T> = RustFft<T>;
+
+
+/// Determine if a number is a power of two
+pub(crate) fn is_power_of_two(x: usize) -> bool {
+    (x != 0) && (x != 1) && ((x & (x - 1)) == 0)
+}
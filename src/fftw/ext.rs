@@ -1,5 +1,5 @@
 extern crate libc;
-use self::libc::{c_int, size_t, c_void};
+use self::libc::{c_int, c_char, size_t, c_void};
 use fftw::types::*;
 
 /// An opaque pointer to an FFTW C plan
@@ -8,7 +8,28 @@ pub enum FftwPlan {}
 #[link(name="fftw3")]
 extern {
     /// Creates a 1-dimensional real-to-complex FFT plan
-    pub fn fftw_plan_dft_r2c_1d(n: c_int, input: *mut f64, output: *mut FftwComplex, flags: PlannerFlags) -> *mut FftwPlan;
+    pub fn fftw_plan_dft_r2c_1d(n: c_int, input: *mut f64, output: *mut FftwComplex<f64>, flags: PlannerFlags) -> *mut FftwPlan;
+
+    /// Creates a 1-dimensional complex-to-complex FFT plan. sign is
+    /// FftwDirection::Forward or FftwDirection::Backward.
+    pub fn fftw_plan_dft_1d(n: c_int, input: *mut FftwComplex<f64>, output: *mut FftwComplex<f64>, sign: FftwDirection, flags: PlannerFlags) -> *mut FftwPlan;
+
+    /// Creates a 1-dimensional complex-to-real (inverse) FFT plan. n is the
+    /// size of the real output; the input holds n/2+1 complex values.
+    pub fn fftw_plan_dft_c2r_1d(n: c_int, input: *mut FftwComplex<f64>, output: *mut f64, flags: PlannerFlags) -> *mut FftwPlan;
+
+    /// Creates a 1-dimensional real-to-real plan, such as a DCT or DST
+    pub fn fftw_plan_r2r_1d(n: c_int, input: *mut f64, output: *mut f64, kind: R2RKind, flags: PlannerFlags) -> *mut FftwPlan;
+
+    /// Creates a plan for many real-to-complex FFTs of the same size at once.
+    /// Element j of transform k is read from input[k*idist + j*istride] and
+    /// written to output[k*odist + j*ostride]. Passing null for inembed and
+    /// onembed means the arrays are exactly n long.
+    /// See: http://www.fftw.org/doc/Advanced-Real_002ddata-DFTs.html
+    pub fn fftw_plan_many_dft_r2c(rank: c_int, n: *const c_int, howmany: c_int,
+                                  input: *mut f64, inembed: *const c_int, istride: c_int, idist: c_int,
+                                  output: *mut FftwComplex<f64>, onembed: *const c_int, ostride: c_int, odist: c_int,
+                                  flags: PlannerFlags) -> *mut FftwPlan;
 
     /// Executes an FFTW plan
     pub fn fftw_execute(plan: *const FftwPlan);
@@ -21,4 +42,62 @@ extern {
 
     /// Destroy an fftw plan. Should be called when done with a plan.
     pub fn fftw_destroy_plan(plan: *mut FftwPlan);
+
+    /// Adds the wisdom stored in a file to the planner. Returns non-zero on
+    /// success. See: http://www.fftw.org/doc/Wisdom-Import.html
+    pub fn fftw_import_wisdom_from_filename(filename: *const c_char) -> c_int;
+
+    /// Saves all of the planner's wisdom to a file. Returns non-zero on
+    /// success. See: http://www.fftw.org/doc/Wisdom-Export.html
+    pub fn fftw_export_wisdom_to_filename(filename: *const c_char) -> c_int;
+}
+
+
+// Single precision versions of the functions above, from libfftw3f. Memory
+// is still allocated with fftw_malloc, which aligns for either precision.
+#[cfg(any(feature = "fftw3f", feature = "single-precision"))]
+#[link(name="fftw3f")]
+extern "C" {
+    pub fn fftwf_plan_dft_r2c_1d(n: c_int, input: *mut f32, output: *mut FftwComplex<f32>, flags: PlannerFlags) -> *mut FftwPlan;
+
+    pub fn fftwf_plan_dft_1d(n: c_int, input: *mut FftwComplex<f32>, output: *mut FftwComplex<f32>, sign: FftwDirection, flags: PlannerFlags) -> *mut FftwPlan;
+
+    pub fn fftwf_plan_dft_c2r_1d(n: c_int, input: *mut FftwComplex<f32>, output: *mut f32, flags: PlannerFlags) -> *mut FftwPlan;
+
+    pub fn fftwf_plan_r2r_1d(n: c_int, input: *mut f32, output: *mut f32, kind: R2RKind, flags: PlannerFlags) -> *mut FftwPlan;
+
+    pub fn fftwf_plan_many_dft_r2c(rank: c_int, n: *const c_int, howmany: c_int,
+                                   input: *mut f32, inembed: *const c_int, istride: c_int, idist: c_int,
+                                   output: *mut FftwComplex<f32>, onembed: *const c_int, ostride: c_int, odist: c_int,
+                                   flags: PlannerFlags) -> *mut FftwPlan;
+
+    pub fn fftwf_execute(plan: *const FftwPlan);
+
+    pub fn fftwf_destroy_plan(plan: *mut FftwPlan);
+
+    pub fn fftwf_import_wisdom_from_filename(filename: *const c_char) -> c_int;
+
+    pub fn fftwf_export_wisdom_to_filename(filename: *const c_char) -> c_int;
+}
+
+
+// Thread support lives in its own library, libfftw3_threads. Both calls affect
+// every plan created afterwards. See: http://www.fftw.org/doc/Usage-of-Multi_002dthreaded-FFTW.html
+#[cfg(feature = "fftw-threads")]
+#[link(name="fftw3_threads")]
+extern "C" {
+    /// Sets up FFTW's threads. Returns zero if that failed.
+    pub fn fftw_init_threads() -> c_int;
+
+    /// Sets the number of threads plans created after this will use
+    pub fn fftw_plan_with_nthreads(nthreads: c_int);
+}
+
+
+#[cfg(all(any(feature = "fftw3f", feature = "single-precision"), feature = "fftw-threads"))]
+#[link(name="fftw3f_threads")]
+extern "C" {
+    pub fn fftwf_init_threads() -> c_int;
+
+    pub fn fftwf_plan_with_nthreads(nthreads: c_int);
 }
@@ -0,0 +1,240 @@
+extern crate libc;
+#[cfg(feature = "fftw")]
+use self::libc::{c_int, c_char};
+use std::fmt::Debug;
+use std::ops::{Add, Sub, Mul, Div, Neg};
+#[cfg(feature = "fftw-threads")]
+use std::sync::atomic::AtomicUsize;
+#[cfg(feature = "fftw")]
+use fftw::ext;
+#[cfg(feature = "fftw-threads")]
+use fftw::threads;
+#[cfg(feature = "fftw")]
+use fftw::types::{FftwComplex, FftwDirection, PlannerFlags, R2RKind};
+
+
+/// The precision used by default throughout the crate. f64 unless the
+/// "single-precision" feature is enabled, which switches to f32 (and libfftw3f
+/// with the FFTW backend) for small CPUs where double precision FFTs waste
+/// cycles and cache.
+#[cfg(not(feature = "single-precision"))]
+pub type FftReal = f64;
+#[cfg(feature = "single-precision")]
+pub type FftReal = f32;
+
+
+/// A floating point type the FFTs can work in, f32 or f64. This is just the
+/// arithmetic the FFT code needs; FftwFloat adds the FFTW functions.
+pub trait FftFloat: Copy + PartialOrd + Debug + 'static
+    + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self> + Div<Output=Self> + Neg<Output=Self> {
+
+    /// Convert from an f64, rounding if this is a smaller type
+    fn from_f64(x: f64) -> Self;
+    /// Convert to an f64
+    fn to_f64(self) -> f64;
+    fn sqrt(self) -> Self;
+    fn log10(self) -> Self;
+    fn abs(self) -> Self;
+}
+
+
+macro_rules! impl_fft_float {
+    ($t:ty) => {
+        impl FftFloat for $t {
+            fn from_f64(x: f64) -> $t { x as $t }
+            fn to_f64(self) -> f64 { self as f64 }
+            fn sqrt(self) -> $t { <$t>::sqrt(self) }
+            fn log10(self) -> $t { <$t>::log10(self) }
+            fn abs(self) -> $t { <$t>::abs(self) }
+        }
+    }
+}
+
+
+impl_fft_float!(f64);
+impl_fft_float!(f32);
+
+
+/// A floating point type FFTW can transform: f64 (libfftw3) or f32
+/// (libfftw3f, with the "fftw3f" or "single-precision" features). Each
+/// implementation maps the plan functions to the right library.
+#[cfg(feature = "fftw")]
+pub trait FftwFloat: FftFloat {
+    /// Plan a real to complex transform of size n.
+    ///
+    /// # Safety
+    ///
+    /// input must point to n writable values and output to n / 2 + 1, both
+    /// allocated with fftw_malloc (see FftwAlignedArray) so the plan's SIMD
+    /// alignment assumptions hold when it's executed. Unless the flags
+    /// include Estimate, planning overwrites both arrays.
+    unsafe fn plan_dft_r2c_1d(n: c_int, input: *mut Self, output: *mut FftwComplex<Self>,
+                              flags: PlannerFlags) -> *mut ext::FftwPlan;
+    /// Plan a complex transform of size n in the given direction.
+    ///
+    /// # Safety
+    ///
+    /// input and output must each point to n fftw_malloc'ed complex values,
+    /// and may be the same array for an in-place transform. Planning
+    /// overwrites them, as for plan_dft_r2c_1d.
+    unsafe fn plan_dft_1d(n: c_int, input: *mut FftwComplex<Self>, output: *mut FftwComplex<Self>,
+                          sign: FftwDirection, flags: PlannerFlags) -> *mut ext::FftwPlan;
+    /// Plan a complex to real transform of size n.
+    ///
+    /// # Safety
+    ///
+    /// input must point to n / 2 + 1 complex values and output to n real
+    /// values, both fftw_malloc'ed. Planning overwrites them, and unlike the
+    /// other transforms executing the plan also overwrites input.
+    unsafe fn plan_dft_c2r_1d(n: c_int, input: *mut FftwComplex<Self>, output: *mut Self,
+                              flags: PlannerFlags) -> *mut ext::FftwPlan;
+    /// Plan a real to real transform of size n of the given kind.
+    ///
+    /// # Safety
+    ///
+    /// input and output must each point to n fftw_malloc'ed values. Planning
+    /// overwrites them, as for plan_dft_r2c_1d.
+    unsafe fn plan_r2r_1d(n: c_int, input: *mut Self, output: *mut Self, kind: R2RKind,
+                          flags: PlannerFlags) -> *mut ext::FftwPlan;
+    /// Plan howmany real to complex transforms at once. See
+    /// ext::fftw_plan_many_dft_r2c for the layout of the arrays.
+    ///
+    /// # Safety
+    ///
+    /// n must point to rank sizes, and inembed and onembed to rank sizes or be
+    /// null. input must be fftw_malloc'ed and hold every index
+    /// k * idist + j * istride for k < howmany and j < n, and output likewise
+    /// with odist, ostride and n / 2 + 1 values per transform. Planning
+    /// overwrites both arrays, as for plan_dft_r2c_1d.
+    // The arguments mirror FFTW's C signature one for one
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn plan_many_dft_r2c(rank: c_int, n: *const c_int, howmany: c_int,
+                                input: *mut Self, inembed: *const c_int, istride: c_int, idist: c_int,
+                                output: *mut FftwComplex<Self>, onembed: *const c_int, ostride: c_int, odist: c_int,
+                                flags: PlannerFlags) -> *mut ext::FftwPlan;
+    /// Run a plan on the arrays it was created with.
+    ///
+    /// # Safety
+    ///
+    /// plan must come from one of the plan functions of the same precision,
+    /// must not have been destroyed, and the arrays it was planned on must
+    /// still be allocated.
+    unsafe fn execute(plan: *const ext::FftwPlan);
+    /// Free a plan.
+    ///
+    /// # Safety
+    ///
+    /// plan must come from one of the plan functions of the same precision
+    /// and mustn't be used or destroyed again afterwards.
+    unsafe fn destroy_plan(plan: *mut ext::FftwPlan);
+
+    /// Each precision has its own planner, and so its own wisdom.
+    ///
+    /// # Safety
+    ///
+    /// filename must be a valid nul-terminated C string.
+    unsafe fn import_wisdom_from_filename(filename: *const c_char) -> c_int;
+    /// Write this precision's wisdom out.
+    ///
+    /// # Safety
+    ///
+    /// filename must be a valid nul-terminated C string.
+    unsafe fn export_wisdom_to_filename(filename: *const c_char) -> c_int;
+    /// The name of the file in the cache directory holding this precision's
+    /// wisdom
+    fn wisdom_file_name() -> &'static str;
+
+    /// Set how many threads plans created after this use. Returns the number
+    /// that will actually be used, which is 1 without the "fftw-threads"
+    /// feature or if FFTW couldn't start its threads.
+    fn set_planner_threads(threads: usize) -> usize;
+}
+
+
+/// Implements FftwFloat for a float type using the functions with the given
+/// prefix. The prefix is spelled out for every function since macro_rules
+/// can't paste identifiers together.
+#[cfg(feature = "fftw")]
+macro_rules! impl_fftw_float {
+    ($t:ty, $r2c:path, $c2c:path, $c2r:path, $r2r:path, $many_r2c:path, $execute:path, $destroy:path,
+     $import:path, $export:path, $wisdom_file:expr, $init_threads:path, $plan_with_nthreads:path) => {
+        impl FftwFloat for $t {
+            unsafe fn plan_dft_r2c_1d(n: c_int, input: *mut $t, output: *mut FftwComplex<$t>,
+                                      flags: PlannerFlags) -> *mut ext::FftwPlan {
+                $r2c(n, input, output, flags)
+            }
+
+            unsafe fn plan_dft_1d(n: c_int, input: *mut FftwComplex<$t>, output: *mut FftwComplex<$t>,
+                                  sign: FftwDirection, flags: PlannerFlags) -> *mut ext::FftwPlan {
+                $c2c(n, input, output, sign, flags)
+            }
+
+            unsafe fn plan_dft_c2r_1d(n: c_int, input: *mut FftwComplex<$t>, output: *mut $t,
+                                      flags: PlannerFlags) -> *mut ext::FftwPlan {
+                $c2r(n, input, output, flags)
+            }
+
+            unsafe fn plan_r2r_1d(n: c_int, input: *mut $t, output: *mut $t, kind: R2RKind,
+                                  flags: PlannerFlags) -> *mut ext::FftwPlan {
+                $r2r(n, input, output, kind, flags)
+            }
+
+            unsafe fn plan_many_dft_r2c(rank: c_int, n: *const c_int, howmany: c_int,
+                                        input: *mut $t, inembed: *const c_int, istride: c_int, idist: c_int,
+                                        output: *mut FftwComplex<$t>, onembed: *const c_int, ostride: c_int, odist: c_int,
+                                        flags: PlannerFlags) -> *mut ext::FftwPlan {
+                $many_r2c(rank, n, howmany, input, inembed, istride, idist, output, onembed, ostride, odist, flags)
+            }
+
+            unsafe fn execute(plan: *const ext::FftwPlan) {
+                $execute(plan)
+            }
+
+            unsafe fn destroy_plan(plan: *mut ext::FftwPlan) {
+                $destroy(plan)
+            }
+
+            unsafe fn import_wisdom_from_filename(filename: *const c_char) -> c_int {
+                $import(filename)
+            }
+
+            unsafe fn export_wisdom_to_filename(filename: *const c_char) -> c_int {
+                $export(filename)
+            }
+
+            fn wisdom_file_name() -> &'static str {
+                $wisdom_file
+            }
+
+            #[cfg(feature = "fftw-threads")]
+            fn set_planner_threads(threads: usize) -> usize {
+                static STATE: AtomicUsize = AtomicUsize::new(threads::UNINITIALIZED);
+                threads::set_planner_threads(threads, $init_threads, $plan_with_nthreads, &STATE)
+            }
+
+            #[cfg(not(feature = "fftw-threads"))]
+            fn set_planner_threads(_threads: usize) -> usize {
+                1
+            }
+        }
+    }
+}
+
+
+#[cfg(feature = "fftw")]
+impl_fftw_float!(f64,
+                 ext::fftw_plan_dft_r2c_1d, ext::fftw_plan_dft_1d, ext::fftw_plan_dft_c2r_1d,
+                 ext::fftw_plan_r2r_1d, ext::fftw_plan_many_dft_r2c,
+                 ext::fftw_execute, ext::fftw_destroy_plan,
+                 ext::fftw_import_wisdom_from_filename, ext::fftw_export_wisdom_to_filename,
+                 "wisdom",
+                 ext::fftw_init_threads, ext::fftw_plan_with_nthreads);
+
+#[cfg(all(feature = "fftw", any(feature = "fftw3f", feature = "single-precision")))]
+impl_fftw_float!(f32,
+                 ext::fftwf_plan_dft_r2c_1d, ext::fftwf_plan_dft_1d, ext::fftwf_plan_dft_c2r_1d,
+                 ext::fftwf_plan_r2r_1d, ext::fftwf_plan_many_dft_r2c,
+                 ext::fftwf_execute, ext::fftwf_destroy_plan,
+                 ext::fftwf_import_wisdom_from_filename, ext::fftwf_export_wisdom_to_filename,
+                 "wisdom-f32",
+                 ext::fftwf_init_threads, ext::fftwf_plan_with_nthreads);
@@ -1,7 +1,18 @@
+#[cfg(feature = "fftw")]
 mod ext;
+#[cfg(feature = "fftw")]
 pub mod aligned_array;
 pub mod types;
+pub mod float;
+pub mod backend;
+#[cfg(feature = "fftw")]
 pub mod plan;
+pub mod rust_fft;
 pub mod multichannel;
 pub mod audio;
 pub mod hanning;
+pub mod window;
+#[cfg(feature = "fftw")]
+pub mod wisdom;
+#[cfg(feature = "fftw")]
+pub mod threads;
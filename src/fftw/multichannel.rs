@@ -1,57 +1,150 @@
-use fftw::plan::*;
+use std::marker::PhantomData;
+use fftw::backend::{DefaultBackend, FftBackend, FftError};
+use fftw::float::{FftFloat, FftReal};
+use fftw::types::{FftwComplex, PlannerFlags};
+#[cfg(all(test, feature = "fftw"))]
+use fftw::plan::FftwBatchPlan;
+#[cfg(all(test, feature = "fftw"))]
+use fftw::threads;
 
 
-/// An FFTW Plan wrapper for multiple channels of data.
-pub struct MultiChannelFft {
+/// An FFT wrapper for multiple channels of data.
+///
+/// All of the channels are handed to the backend as one batch, so a backend
+/// like FFTW plans once and transforms every channel in one call. The channels
+/// are stored one after another: channel n's input is
+/// input[n*size..(n+1)*size], and its output is
+/// output[n*(size/2+1)..(n+1)*(size/2+1)].
+pub struct MultiChannelFft<T: FftFloat = FftReal, B: FftBackend<T> = DefaultBackend<T>> {
     /// The size of the FFTs to be run
     pub size: usize,
     /// The number of channels
     pub channel_count: usize,
-    /// The plans for each channel
-    pub channel_plans: Vec<FftwPlan>
+    /// The FFT implementation, which owns the input and output for every
+    /// channel
+    backend: B,
+    precision: PhantomData<T>,
 }
 
 
-impl MultiChannelFft {
-    //// Create and initialize a new MultiChannelFft
-    pub fn new(size: usize, channel_count: usize) -> MultiChannelFft {
-        let mut channel_plans: Vec<FftwPlan> = Vec::with_capacity(channel_count);
+impl<T: FftFloat, B: FftBackend<T>> MultiChannelFft<T, B> {
+    /// Create and initialize a new MultiChannelFft. Fails if the backend
+    /// can't handle the size; see FftBackend::new.
+    pub fn new(size: usize, channel_count: usize) -> Result<MultiChannelFft<T, B>, FftError> {
+        MultiChannelFft::with_flags(size, channel_count, PlannerFlags::Measure)
+    }
 
-        for _ in 0..channel_count {
-            channel_plans.push(FftwPlan::new(size));
-        }
-        channel_plans.shrink_to_fit();
+    /// Create a MultiChannelFft using the given planner effort
+    pub fn with_flags(size: usize, channel_count: usize, flags: PlannerFlags) -> Result<MultiChannelFft<T, B>, FftError> {
+        MultiChannelFft::with_threads(size, channel_count, flags, 1)
+    }
 
-        MultiChannelFft {
+    /// Create a MultiChannelFft which runs on up to threads threads, or a
+    /// single thread where the backend doesn't support threads
+    pub fn with_threads(size: usize, channel_count: usize, flags: PlannerFlags,
+                        threads: usize) -> Result<MultiChannelFft<T, B>, FftError> {
+        Ok(MultiChannelFft {
             size: size,
             channel_count: channel_count,
-            channel_plans: channel_plans,
+            backend: B::new(size, channel_count, flags, threads)?,
+            precision: PhantomData,
+        })
+    }
+
+    /// Execute all of the FFT channels
+    pub fn execute(&mut self) {
+        self.backend.execute();
+    }
+
+    /// The number of threads the plan runs on
+    pub fn get_thread_count(&self) -> usize {
+        self.backend.get_thread_count()
+    }
+
+    /// Get the input slice for a channel
+    pub fn get_input<'a>(&'a mut self, index: usize) -> &'a mut [T] {
+        let size = self.size;
+        &mut self.backend.get_input_slice()[index * size..(index + 1) * size]
+    }
+
+    /// Gets a vector of all of the input slices
+    pub fn get_inputs<'a>(&'a mut self) -> Vec<&'a mut [T]> {
+        let size = self.size;
+        self.backend.get_input_slice().chunks_mut(size).collect()
+    }
+
+    /// Get the output slice for a channel. Like FftwPlan, this leaves off the
+    /// Nyquist bin, so it is size/2 long.
+    pub fn get_output<'a>(&'a self, index: usize) -> &'a [FftwComplex<T>] {
+        let output_size = self.size/2 + 1;
+        let start = index * output_size;
+        &self.backend.get_output_slice()[start..start + self.size/2]
+    }
+}
+
+
+#[test]
+fn test_channels_are_independent() {
+    let size = 16;
+    let mut fft: MultiChannelFft<f64> = MultiChannelFft::new(size, 3).unwrap();
+    for (channel, input) in fft.get_inputs().into_iter().enumerate() {
+        for value in input.iter_mut() {
+            *value = channel as f64;
         }
     }
+    fft.execute();
 
-    /// Return a borrowed reference to the plan for a channel
-    pub fn get_channel<'a>(&'a self, index: usize) -> Option<&'a FftwPlan> {
-        self.channel_plans.get(index)
+    // A constant signal only has a DC component of size * value
+    for channel in 0..3 {
+        let output = fft.get_output(channel);
+        assert_eq!(output.len(), size/2);
+        assert!((output[0].re - (size * channel) as f64).abs() < 1e-9);
+        for value in output[1..].iter() {
+            assert!(value.abs() < 1e-9);
+        }
     }
+}
 
-    /// Return a mutable borrowed reference to the plan for a channel
-    pub fn get_channel_mut<'a>(&'a mut self, index: usize) -> Option<&'a mut FftwPlan> {
-        self.channel_plans.get_mut(index)
+
+#[cfg(feature = "fftw")]
+#[test]
+fn test_threaded_matches_single_threaded() {
+    let size = 64;
+    let mut single: MultiChannelFft<f64, FftwBatchPlan<f64>> = MultiChannelFft::new(size, 2).unwrap();
+    let mut threaded: MultiChannelFft<f64, FftwBatchPlan<f64>> =
+        MultiChannelFft::with_threads(size, 2, PlannerFlags::Measure, 4).unwrap();
+    if threads::threads_available() {
+        assert_eq!(threaded.get_thread_count(), 4);
+    } else {
+        assert_eq!(threaded.get_thread_count(), 1);
     }
 
-    /// Execute all of the FFT channels
-    pub fn execute(&mut self) {
-        for plan in self.channel_plans.iter_mut() {
-            plan.execute();
+    for fft in [&mut single, &mut threaded].iter_mut() {
+        for (channel, input) in fft.get_inputs().into_iter().enumerate() {
+            for (i, value) in input.iter_mut().enumerate() {
+                *value = ((i * (channel + 3)) % 11) as f64;
+            }
         }
+        fft.execute();
     }
 
-    /// Gets a vector of all of the input slices
-    pub fn get_inputs<'a>(&'a mut self) -> Vec<&'a mut [f64]> {
-        let mut inputs: Vec<&mut [f64]> = Vec::with_capacity(self.channel_count);
-        for channel in self.channel_plans.iter_mut() {
-            inputs.push(channel.get_input_slice());
+    for channel in 0..2 {
+        for (a, b) in single.get_output(channel).iter().zip(threaded.get_output(channel).iter()) {
+            assert!((a.re - b.re).abs() < 1e-9 && (a.im - b.im).abs() < 1e-9);
         }
-        inputs
     }
 }
+
+
+#[cfg(feature = "fftw")]
+#[test]
+fn test_non_power_of_two_channels() {
+    // 4800 points is 100ms at 48kHz, for bins 10Hz apart
+    let size = 4800;
+    let mut fft: MultiChannelFft<f64, FftwBatchPlan<f64>> = MultiChannelFft::new(size, 2).unwrap();
+    fft.get_input(1)[0] = 1.0;
+    fft.execute();
+    assert_eq!(fft.get_output(1).len(), size/2);
+    assert!(fft.get_output(0).iter().all(|value| value.abs() == 0.0));
+    assert!(fft.get_output(1).iter().all(|value| (value.re - 1.0).abs() < 1e-9));
+}
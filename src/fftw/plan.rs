@@ -1,76 +1,392 @@
 extern crate libc;
-use fftw::types::{FftwComplex, PlannerFlags};
+use std::marker::PhantomData;
+use std::ptr;
+use fftw::types::{FftwComplex, FftwDirection, PlannerFlags, R2RKind};
 use fftw::ext;
 use fftw::aligned_array::FftwAlignedArray;
+use fftw::backend::{FftBackend, FftError};
+#[cfg(test)]
+use fftw::backend::is_power_of_two;
+use fftw::float::{FftwFloat, FftReal};
+
+
+/// Owns a raw FFTW plan and destroys it when dropped. Shared by all of the
+/// plan wrappers in the fftw module. T picks the library (fftw or fftwf) the
+/// plan came from, since plans can't be mixed between them.
+pub(crate) struct PlanHandle<T: FftwFloat> {
+    plan: *mut ext::FftwPlan,
+    threads: usize,
+    precision: PhantomData<T>,
+}
+
+
+impl<T: FftwFloat> PlanHandle<T> {
+    /// Wrap a plan returned by one of the fftw_plan functions. Fails if FFTW
+    /// couldn't create the plan.
+    pub(crate) fn new(plan: *mut ext::FftwPlan) -> Result<PlanHandle<T>, FftError> {
+        if plan.is_null() {
+            return Err(FftError::PlanFailed);
+        }
+        Ok(PlanHandle { plan: plan, threads: 1, precision: PhantomData })
+    }
+
+    /// Make a plan which uses up to the given number of threads. make_plan
+    /// should call one of the fftw_plan functions. The thread count goes back
+    /// to 1 afterwards so it doesn't leak into other plans.
+    pub(crate) fn with_threads<F>(threads: usize, make_plan: F) -> Result<PlanHandle<T>, FftError>
+        where F: FnOnce() -> *mut ext::FftwPlan {
+        let used = T::set_planner_threads(threads);
+        let plan = make_plan();
+        if used > 1 {
+            T::set_planner_threads(1);
+        }
+        let mut handle = PlanHandle::new(plan)?;
+        handle.threads = used;
+        Ok(handle)
+    }
+
+    /// The number of threads the plan actually runs on
+    pub(crate) fn get_thread_count(&self) -> usize {
+        self.threads
+    }
+
+    /// Execute the plan on the arrays it was created with
+    pub(crate) fn execute(&mut self) {
+        unsafe { T::execute(self.plan) };
+    }
+}
+
+
+/// Unsafe because it has lifetimes.
+impl<T: FftwFloat> Drop for PlanHandle<T> {
+    /// Runds fftw_destroy plan when a plan goes out of scope
+    fn drop(&mut self) {
+        unsafe { T::destroy_plan(self.plan); }
+    }
+}
 
 
 /// Rust wrapper for an FFTW plan
-pub struct FftwPlan {
-    input: FftwAlignedArray<f64>,
-    output: FftwAlignedArray<FftwComplex>,
+pub struct FftwPlan<T: FftwFloat = FftReal> {
+    input: FftwAlignedArray<T>,
+    output: FftwAlignedArray<FftwComplex<T>>,
     size: usize,
-    plan: *mut ext::FftwPlan,
+    plan: PlanHandle<T>,
 }
 
 
-impl FftwPlan {
+impl<T: FftwFloat> FftwPlan<T> {
     /// Create a new wrapper around an FFTW plan
-    pub fn new(size: usize) -> FftwPlan {
-        if !is_power_of_two(size) {
-            panic!("FFT size should be a power of two!");
-        }
+    pub fn new(size: usize) -> Result<FftwPlan<T>, FftError> {
+        FftwPlan::with_flags(size, PlannerFlags::Measure)
+    }
+
+    /// Create a plan with a different planner effort than Measure. Fails if
+    /// FFTW can't make a plan, which happens with WisdomOnly when there is no
+    /// wisdom for this size.
+    pub fn with_flags(size: usize, flags: PlannerFlags) -> Result<FftwPlan<T>, FftError> {
+        FftwPlan::with_threads(size, flags, 1)
+    }
 
+    /// Create a plan which splits each execution over up to threads threads.
+    /// Falls back to a single thread if the "fftw-threads" feature is off or
+    /// FFTW couldn't start its threads; see get_thread_count.
+    ///
+    /// FFTW handles any size but 0, though sizes with only small prime
+    /// factors (2, 3, 5, 7) are fastest.
+    pub fn with_threads(size: usize, flags: PlannerFlags, threads: usize) -> Result<FftwPlan<T>, FftError> {
+        if size == 0 {
+            return Err(FftError::UnsupportedSize(size));
+        }
         let mut input = FftwAlignedArray::new(size);
-        input.initialize(0.0);
         let mut output = FftwAlignedArray::new(size);
-        output.initialize(FftwComplex{re: 0.0, im: 0.0});
 
-        let plan = unsafe {
-            ext::fftw_plan_dft_r2c_1d(
+        let plan = PlanHandle::with_threads(threads, || unsafe {
+            T::plan_dft_r2c_1d(
                 size as i32,
                 input.as_mut_ptr(),
                 output.as_mut_ptr(),
-                PlannerFlags::Measure
+                flags
             )
-        };
+        })?;
 
-        FftwPlan {
+        // Measuring scribbles over the arrays, so initialize them afterwards
+        input.initialize(T::from_f64(0.0));
+        output.initialize(FftwComplex::zero());
+
+        Ok(FftwPlan {
             input: input,
             output: output,
             size: size,
             plan: plan
-        }
+        })
     }
 
     /// Execute the plan
     pub fn execute(&mut self) {
-        unsafe { ext::fftw_execute(self.plan) };
+        self.plan.execute();
+    }
+
+    /// The number of threads the plan runs on
+    pub fn get_thread_count(&self) -> usize {
+        self.plan.get_thread_count()
     }
 
     /// Get a slice of the FFTW plan's input buffer
-    pub fn get_input_slice<'a>(&'a mut self) -> &'a mut [f64] {
+    pub fn get_input_slice<'a>(&'a mut self) -> &'a mut [T] {
         self.input.as_mut_slice()
     }
 
     /// Get a slice of the FFTW plan's output buffer
-    pub fn get_output_slice<'a>(&'a self) -> &'a [FftwComplex] {
+    pub fn get_output_slice<'a>(&'a self) -> &'a [FftwComplex<T>] {
         // A real FFT outputs half of the input size.
         &self.output.as_slice()[0..(self.size/2)]
     }
 }
 
-/// Unsafe because it has lifetimes.
-impl Drop for FftwPlan {
-    /// Runds fftw_destroy plan when a plan goes out of scope
-    fn drop(&mut self) {
-        unsafe { ext::fftw_destroy_plan(self.plan); }
+
+/// The FFTW implementation of FftBackend. Rather than one plan per channel,
+/// this makes a single batched plan with fftw_plan_many_dft_r2c over one
+/// contiguous input array, so FFTW plans once and transforms every channel in
+/// one call.
+pub struct FftwBatchPlan<T: FftwFloat = FftReal> {
+    input: FftwAlignedArray<T>,
+    output: FftwAlignedArray<FftwComplex<T>>,
+    plan: PlanHandle<T>,
+}
+
+
+impl<T: FftwFloat> FftBackend<T> for FftwBatchPlan<T> {
+    /// FFTW can plan any size but 0
+    fn new(size: usize, count: usize, flags: PlannerFlags, threads: usize) -> Result<FftwBatchPlan<T>, FftError> {
+        if size == 0 {
+            return Err(FftError::UnsupportedSize(size));
+        }
+        let output_size = size/2 + 1;
+        let mut input = FftwAlignedArray::new(size * count);
+        let mut output = FftwAlignedArray::new(output_size * count);
+
+        let n = size as i32;
+        let plan = PlanHandle::with_threads(threads, || unsafe {
+            T::plan_many_dft_r2c(
+                1, &n, count as i32,
+                input.as_mut_ptr(), ptr::null(), 1, size as i32,
+                output.as_mut_ptr(), ptr::null(), 1, output_size as i32,
+                flags
+            )
+        })?;
+
+        // Measuring scribbles over the arrays, so initialize them afterwards
+        input.initialize(T::from_f64(0.0));
+        output.initialize(FftwComplex::zero());
+
+        Ok(FftwBatchPlan {
+            input: input,
+            output: output,
+            plan: plan,
+        })
+    }
+
+    fn execute(&mut self) {
+        self.plan.execute();
+    }
+
+    fn get_input_slice<'a>(&'a mut self) -> &'a mut [T] {
+        self.input.as_mut_slice()
+    }
+
+    fn get_output_slice<'a>(&'a self) -> &'a [FftwComplex<T>] {
+        self.output.as_slice()
+    }
+
+    fn get_thread_count(&self) -> usize {
+        self.plan.get_thread_count()
+    }
+}
+
+
+/// A complex-to-complex FFT plan. Runs in either direction; neither direction
+/// is normalized, so a forward then backward transform scales by size.
+pub struct FftwComplexPlan<T: FftwFloat = FftReal> {
+    input: FftwAlignedArray<FftwComplex<T>>,
+    output: FftwAlignedArray<FftwComplex<T>>,
+    direction: FftwDirection,
+    plan: PlanHandle<T>,
+}
+
+
+impl<T: FftwFloat> FftwComplexPlan<T> {
+    /// Create a complex plan of the given size and direction. FFTW handles any
+    /// size but 0.
+    pub fn new(size: usize, direction: FftwDirection) -> Result<FftwComplexPlan<T>, FftError> {
+        if size == 0 {
+            return Err(FftError::UnsupportedSize(size));
+        }
+        let mut input = FftwAlignedArray::new(size);
+        let mut output = FftwAlignedArray::new(size);
+
+        let plan = PlanHandle::new(unsafe {
+            T::plan_dft_1d(
+                size as i32,
+                input.as_mut_ptr(),
+                output.as_mut_ptr(),
+                direction,
+                PlannerFlags::Measure
+            )
+        })?;
+
+        input.initialize(FftwComplex::zero());
+        output.initialize(FftwComplex::zero());
+
+        Ok(FftwComplexPlan {
+            input: input,
+            output: output,
+            direction: direction,
+            plan: plan
+        })
+    }
+
+    /// The direction this plan was created with
+    pub fn get_direction(&self) -> FftwDirection {
+        self.direction
+    }
+
+    /// Execute the plan
+    pub fn execute(&mut self) {
+        self.plan.execute();
+    }
+
+    /// Get a slice of the plan's input buffer
+    pub fn get_input_slice<'a>(&'a mut self) -> &'a mut [FftwComplex<T>] {
+        self.input.as_mut_slice()
+    }
+
+    /// Get a slice of the plan's output buffer
+    pub fn get_output_slice<'a>(&'a self) -> &'a [FftwComplex<T>] {
+        self.output.as_slice()
     }
 }
 
 
-/// Determine if a number is a power of two
-fn is_power_of_two(x: usize) -> bool {
-    (x != 0) && (x != 1) && ((x & (x - 1)) == 0)
+/// A complex-to-real plan, the inverse of FftwPlan. Takes the size/2+1
+/// non-redundant outputs of a real FFT and produces size real values, scaled
+/// by size since FFTW doesn't normalize.
+///
+/// Note: executing the plan overwrites the input buffer, so it has to be
+/// refilled before every execution.
+pub struct FftwInversePlan<T: FftwFloat = FftReal> {
+    input: FftwAlignedArray<FftwComplex<T>>,
+    output: FftwAlignedArray<T>,
+    plan: PlanHandle<T>,
+}
+
+
+impl<T: FftwFloat> FftwInversePlan<T> {
+    /// Create an inverse plan which produces size real values. FFTW handles
+    /// any size but 0.
+    pub fn new(size: usize) -> Result<FftwInversePlan<T>, FftError> {
+        if size == 0 {
+            return Err(FftError::UnsupportedSize(size));
+        }
+        let mut input = FftwAlignedArray::new(size/2 + 1);
+        let mut output = FftwAlignedArray::new(size);
+
+        let plan = PlanHandle::new(unsafe {
+            T::plan_dft_c2r_1d(
+                size as i32,
+                input.as_mut_ptr(),
+                output.as_mut_ptr(),
+                PlannerFlags::Measure
+            )
+        })?;
+
+        input.initialize(FftwComplex::zero());
+        output.initialize(T::from_f64(0.0));
+
+        Ok(FftwInversePlan {
+            input: input,
+            output: output,
+            plan: plan
+        })
+    }
+
+    /// Execute the plan
+    pub fn execute(&mut self) {
+        self.plan.execute();
+    }
+
+    /// Get a slice of the plan's size/2+1 complex inputs
+    pub fn get_input_slice<'a>(&'a mut self) -> &'a mut [FftwComplex<T>] {
+        self.input.as_mut_slice()
+    }
+
+    /// Get a slice of the plan's real output buffer
+    pub fn get_output_slice<'a>(&'a self) -> &'a [T] {
+        self.output.as_slice()
+    }
+}
+
+
+/// A real-to-real plan, such as a DCT or DST. See R2RKind for the available
+/// transforms. Like the other plans, the output is not normalized.
+pub struct FftwR2RPlan<T: FftwFloat = FftReal> {
+    input: FftwAlignedArray<T>,
+    output: FftwAlignedArray<T>,
+    kind: R2RKind,
+    plan: PlanHandle<T>,
+}
+
+
+impl<T: FftwFloat> FftwR2RPlan<T> {
+    /// Create a real-to-real plan of the given size and kind. FFTW handles any
+    /// size but 0.
+    pub fn new(size: usize, kind: R2RKind) -> Result<FftwR2RPlan<T>, FftError> {
+        if size == 0 {
+            return Err(FftError::UnsupportedSize(size));
+        }
+        let mut input = FftwAlignedArray::new(size);
+        let mut output = FftwAlignedArray::new(size);
+
+        let plan = PlanHandle::new(unsafe {
+            T::plan_r2r_1d(
+                size as i32,
+                input.as_mut_ptr(),
+                output.as_mut_ptr(),
+                kind,
+                PlannerFlags::Measure
+            )
+        })?;
+
+        input.initialize(T::from_f64(0.0));
+        output.initialize(T::from_f64(0.0));
+
+        Ok(FftwR2RPlan {
+            input: input,
+            output: output,
+            kind: kind,
+            plan: plan
+        })
+    }
+
+    /// The kind of transform this plan performs
+    pub fn get_kind(&self) -> R2RKind {
+        self.kind
+    }
+
+    /// Execute the plan
+    pub fn execute(&mut self) {
+        self.plan.execute();
+    }
+
+    /// Get a slice of the plan's input buffer
+    pub fn get_input_slice<'a>(&'a mut self) -> &'a mut [T] {
+        self.input.as_mut_slice()
+    }
+
+    /// Get a slice of the plan's output buffer
+    pub fn get_output_slice<'a>(&'a self) -> &'a [T] {
+        self.output.as_slice()
+    }
 }
 
 
@@ -87,3 +403,106 @@ fn test_pwer_two() {
     assert!(!is_power_of_two(7));
     assert!(!is_power_of_two(500));
 }
+
+
+#[test]
+fn test_inverse_round_trip() {
+    let size = 64;
+    let signal: Vec<f64> = (0..size).map(|i| ((i * 7) % 13) as f64 - 6.0).collect();
+
+    let mut forward: FftwPlan<f64> = FftwPlan::new(size).unwrap();
+    forward.get_input_slice().copy_from_slice(&signal);
+    forward.execute();
+
+    // get_output_slice leaves off the Nyquist bin, so copy the raw output
+    let mut inverse: FftwInversePlan<f64> = FftwInversePlan::new(size).unwrap();
+    inverse.get_input_slice().copy_from_slice(&forward.output.as_slice()[0..size/2 + 1]);
+    inverse.execute();
+
+    for (&out, &expected) in inverse.get_output_slice().iter().zip(signal.iter()) {
+        assert!((out / size as f64 - expected).abs() < 1e-9);
+    }
+}
+
+
+#[test]
+fn test_complex_and_dct() {
+    let size = 8;
+
+    // A forward complex FFT of an impulse is flat
+    let mut complex: FftwComplexPlan<f64> = FftwComplexPlan::new(size, FftwDirection::Forward).unwrap();
+    complex.get_input_slice()[0] = FftwComplex{re: 1.0, im: 0.0};
+    complex.execute();
+    for value in complex.get_output_slice().iter() {
+        assert!((value.re - 1.0).abs() < 1e-12 && value.im.abs() < 1e-12);
+    }
+
+    // The DCT-II of a constant only has a DC term, 2 * n * value
+    let mut dct: FftwR2RPlan<f64> = FftwR2RPlan::new(size, R2RKind::Redft10).unwrap();
+    dct.get_input_slice().copy_from_slice(&[1.0; 8]);
+    dct.execute();
+    assert!((dct.get_output_slice()[0] - 16.0).abs() < 1e-12);
+    for &value in dct.get_output_slice()[1..].iter() {
+        assert!(value.abs() < 1e-12);
+    }
+}
+
+
+#[cfg(any(feature = "fftw3f", feature = "single-precision"))]
+#[test]
+fn test_single_precision_round_trip() {
+    let size = 64;
+    let signal: Vec<f32> = (0..size).map(|i| ((i * 7) % 13) as f32 - 6.0).collect();
+
+    let mut forward: FftwPlan<f32> = FftwPlan::new(size).unwrap();
+    forward.get_input_slice().copy_from_slice(&signal);
+    forward.execute();
+
+    let mut inverse: FftwInversePlan<f32> = FftwInversePlan::new(size).unwrap();
+    inverse.get_input_slice().copy_from_slice(&forward.output.as_slice()[0..size/2 + 1]);
+    inverse.execute();
+
+    for (&out, &expected) in inverse.get_output_slice().iter().zip(signal.iter()) {
+        assert!((out / size as f32 - expected).abs() < 1e-4);
+    }
+}
+
+
+#[test]
+fn test_wisdom_only_without_wisdom() {
+    // Nothing has planned this size, so there's no wisdom for it
+    let plan: Result<FftwBatchPlan<f64>, FftError> = FftwBatchPlan::new(1234, 3, PlannerFlags::WisdomOnly, 1);
+    assert_eq!(plan.err(), Some(FftError::PlanFailed));
+    let plan: Result<FftwPlan<f64>, FftError> = FftwPlan::with_flags(1234, PlannerFlags::WisdomOnly);
+    assert_eq!(plan.err(), Some(FftError::PlanFailed));
+    let plan: Result<FftwPlan<f64>, FftError> = FftwPlan::new(0);
+    assert_eq!(plan.err(), Some(FftError::UnsupportedSize(0)));
+}
+
+
+#[test]
+fn test_zero_size() {
+    let complex: Result<FftwComplexPlan<f64>, FftError> = FftwComplexPlan::new(0, FftwDirection::Forward);
+    assert_eq!(complex.err(), Some(FftError::UnsupportedSize(0)));
+    let inverse: Result<FftwInversePlan<f64>, FftError> = FftwInversePlan::new(0);
+    assert_eq!(inverse.err(), Some(FftError::UnsupportedSize(0)));
+    let r2r: Result<FftwR2RPlan<f64>, FftError> = FftwR2RPlan::new(0, R2RKind::Redft10);
+    assert_eq!(r2r.err(), Some(FftError::UnsupportedSize(0)));
+}
+
+
+#[test]
+fn test_non_power_of_two() {
+    // 1000 points at 48kHz puts the bins exactly 48Hz apart
+    let size = 1000;
+    let mut plan: FftwPlan<f64> = FftwPlan::new(size).unwrap();
+    for (i, value) in plan.get_input_slice().iter_mut().enumerate() {
+        *value = (2.0 * ::std::f64::consts::PI * 10.0 * i as f64 / size as f64).cos();
+    }
+    plan.execute();
+
+    let output = plan.get_output_slice();
+    assert_eq!(output.len(), size/2);
+    assert!((output[10].re - size as f64 / 2.0).abs() < 1e-6);
+    assert!(output[11].abs() < 1e-6);
+}
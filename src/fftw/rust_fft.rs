@@ -0,0 +1,206 @@
+use std::f64::consts::PI;
+use fftw::backend::{FftBackend, FftError, is_power_of_two};
+use fftw::float::{FftFloat, FftReal};
+use fftw::types::{FftwComplex, PlannerFlags};
+#[cfg(all(test, feature = "fftw"))]
+use fftw::plan::FftwBatchPlan;
+
+
+/// A pure Rust real FFT backend, for builds without FFTW.
+///
+/// A real signal of size n is packed into a complex signal of n/2 points, even
+/// samples as the real parts and odd samples as the imaginary parts. That goes
+/// through an iterative radix-2 complex FFT, and then the spectra of the even
+/// and odd samples are untangled and combined into the n/2+1 outputs. This
+/// only handles power of two sizes.
+pub struct RustFft<T: FftFloat = FftReal> {
+    size: usize,
+    count: usize,
+    input: Vec<T>,
+    output: Vec<FftwComplex<T>>,
+    /// Working space for the half size complex FFT
+    scratch: Vec<FftwComplex<T>>,
+    /// exp(-2 pi i k / (size/2)) for the butterflies of the complex FFT
+    twiddles: Vec<FftwComplex<T>>,
+    /// exp(-2 pi i k / size) for combining the even and odd spectra
+    split_twiddles: Vec<FftwComplex<T>>,
+    /// Where each packed sample goes so the butterflies can run in place
+    bit_reverse: Vec<usize>,
+}
+
+
+impl<T: FftFloat> FftBackend<T> for RustFft<T> {
+    /// Only powers of two are supported
+    fn new(size: usize, count: usize, _flags: PlannerFlags, _threads: usize) -> Result<RustFft<T>, FftError> {
+        if !is_power_of_two(size) {
+            return Err(FftError::UnsupportedSize(size));
+        }
+        let half = size / 2;
+
+        let bits = half.trailing_zeros();
+        let bit_reverse = (0..half)
+            .map(|i| if bits == 0 { 0 } else { i.reverse_bits() >> (usize::MAX.count_ones() - bits) })
+            .collect();
+
+        Ok(RustFft {
+            size: size,
+            count: count,
+            input: vec![T::from_f64(0.0); size * count],
+            output: vec![FftwComplex::zero(); (half + 1) * count],
+            scratch: vec![FftwComplex::zero(); half],
+            twiddles: (0..half / 2).map(|k| twiddle(k, half)).collect(),
+            split_twiddles: (0..half + 1).map(|k| twiddle(k, size)).collect(),
+            bit_reverse: bit_reverse,
+        })
+    }
+
+    fn execute(&mut self) {
+        let half = self.size / 2;
+        for channel in 0..self.count {
+            // Pack pairs of real samples into complex values, in bit reversed
+            // order
+            let input = &self.input[channel * self.size..(channel + 1) * self.size];
+            for (k, &dest) in self.bit_reverse.iter().enumerate() {
+                self.scratch[dest] = FftwComplex{re: input[2 * k], im: input[2 * k + 1]};
+            }
+
+            self.complex_fft();
+
+            // Z[k] = E[k] + i*O[k], where E and O are the spectra of the even
+            // and odd samples. Both are conjugate symmetric, so they can be
+            // pulled back apart using Z[half - k].
+            let output = &mut self.output[channel * (half + 1)..(channel + 1) * (half + 1)];
+            let one_half = T::from_f64(0.5);
+            for (k, (out, &split_twiddle)) in output.iter_mut().zip(self.split_twiddles.iter()).enumerate() {
+                let z = self.scratch[k % half];
+                let z_mirror = conj(self.scratch[(half - k) % half]);
+                let sum = add(z, z_mirror);
+                let diff = sub(z, z_mirror);
+                let even = FftwComplex{re: sum.re * one_half, im: sum.im * one_half};
+                let odd = FftwComplex{re: diff.im * one_half, im: -diff.re * one_half};
+                *out = add(even, mul(split_twiddle, odd));
+            }
+        }
+    }
+
+    fn get_input_slice<'a>(&'a mut self) -> &'a mut [T] {
+        &mut self.input[..]
+    }
+
+    fn get_output_slice<'a>(&'a self) -> &'a [FftwComplex<T>] {
+        &self.output[..]
+    }
+}
+
+
+impl<T: FftFloat> RustFft<T> {
+    /// Radix-2 decimation in time FFT over the bit reversed scratch buffer
+    fn complex_fft(&mut self) {
+        let n = self.scratch.len();
+        let mut len = 2;
+        while len <= n {
+            let step = n / len;
+            for start in (0..n).step_by(len) {
+                for j in 0..len / 2 {
+                    let a = self.scratch[start + j];
+                    let b = mul(self.scratch[start + j + len / 2], self.twiddles[j * step]);
+                    self.scratch[start + j] = add(a, b);
+                    self.scratch[start + j + len / 2] = sub(a, b);
+                }
+            }
+            len *= 2;
+        }
+    }
+}
+
+
+/// exp(-2 pi i k / n), computed in f64 for accuracy
+fn twiddle<T: FftFloat>(k: usize, n: usize) -> FftwComplex<T> {
+    let angle = -2.0 * PI * k as f64 / n as f64;
+    FftwComplex{re: T::from_f64(angle.cos()), im: T::from_f64(angle.sin())}
+}
+
+
+fn add<T: FftFloat>(a: FftwComplex<T>, b: FftwComplex<T>) -> FftwComplex<T> {
+    FftwComplex{re: a.re + b.re, im: a.im + b.im}
+}
+
+
+fn sub<T: FftFloat>(a: FftwComplex<T>, b: FftwComplex<T>) -> FftwComplex<T> {
+    FftwComplex{re: a.re - b.re, im: a.im - b.im}
+}
+
+
+fn mul<T: FftFloat>(a: FftwComplex<T>, b: FftwComplex<T>) -> FftwComplex<T> {
+    FftwComplex{re: a.re * b.re - a.im * b.im, im: a.re * b.im + a.im * b.re}
+}
+
+
+fn conj<T: FftFloat>(a: FftwComplex<T>) -> FftwComplex<T> {
+    FftwComplex{re: a.re, im: -a.im}
+}
+
+
+#[cfg(test)]
+use test_signal::noise;
+
+
+/// A deterministic, noisy test signal centered on 0
+#[cfg(test)]
+fn test_signal(len: usize, seed: usize) -> Vec<f64> {
+    noise(len, seed).into_iter().map(|x| x - 0.5).collect()
+}
+
+
+#[test]
+fn test_matches_naive_dft() {
+    for &size in [2, 4, 8, 16, 64, 256].iter() {
+        let signal = test_signal(size, size);
+        let mut fft: RustFft<f64> = RustFft::new(size, 1, PlannerFlags::Measure, 1).unwrap();
+        fft.get_input_slice().copy_from_slice(&signal);
+        fft.execute();
+
+        for (k, value) in fft.get_output_slice().iter().enumerate() {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (t, &x) in signal.iter().enumerate() {
+                let angle = -2.0 * PI * (k * t) as f64 / size as f64;
+                re += x * angle.cos();
+                im += x * angle.sin();
+            }
+            assert!((value.re - re).abs() < 1e-9 && (value.im - im).abs() < 1e-9,
+                    "size {} bin {}: {:?} != {} + {}i", size, k, value, re, im);
+        }
+    }
+}
+
+
+#[cfg(feature = "fftw")]
+#[test]
+fn test_matches_fftw() {
+    let channels = 3;
+    for &size in [2, 8, 32, 128, 512].iter() {
+        let mut rust: RustFft<f64> = RustFft::new(size, channels, PlannerFlags::Estimate, 1).unwrap();
+        let mut fftw: FftwBatchPlan<f64> = FftwBatchPlan::new(size, channels, PlannerFlags::Estimate, 1).unwrap();
+        let signal = test_signal(size * channels, size);
+        rust.get_input_slice().copy_from_slice(&signal);
+        fftw.get_input_slice().copy_from_slice(&signal);
+        rust.execute();
+        fftw.execute();
+
+        assert_eq!(rust.get_output_slice().len(), fftw.get_output_slice().len());
+        let tolerance = 1e-9 * size as f64;
+        for (a, b) in rust.get_output_slice().iter().zip(fftw.get_output_slice().iter()) {
+            assert!((a.re - b.re).abs() < tolerance && (a.im - b.im).abs() < tolerance,
+                    "size {}: {:?} != {:?}", size, a, b);
+        }
+    }
+}
+
+
+#[test]
+fn test_rejects_other_sizes() {
+    for &size in [0, 1, 1000, 4800].iter() {
+        let fft: Result<RustFft<f64>, FftError> = RustFft::new(size, 2, PlannerFlags::Measure, 1);
+        assert_eq!(fft.err(), Some(FftError::UnsupportedSize(size)));
+    }
+}
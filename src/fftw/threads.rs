@@ -0,0 +1,52 @@
+extern crate libc;
+#[cfg(feature = "fftw-threads")]
+use self::libc::c_int;
+#[cfg(feature = "fftw-threads")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+/// fftw_init_threads hasn't been called yet
+#[cfg(feature = "fftw-threads")]
+pub(crate) const UNINITIALIZED: usize = 0;
+/// FFTW's threads are running
+#[cfg(feature = "fftw-threads")]
+const INITIALIZED: usize = 1;
+/// fftw_init_threads failed, so plans stay single threaded
+#[cfg(feature = "fftw-threads")]
+const FAILED: usize = 2;
+
+
+/// Whether this build can make multithreaded plans. Threaded plans are only
+/// worth it for large FFTs, tens of thousands of points or more; for the
+/// 1024 point FFTs used for bars, starting the threads costs more than the
+/// FFT itself.
+pub fn threads_available() -> bool {
+    cfg!(feature = "fftw-threads")
+}
+
+
+/// Shared body of FftwFloat::set_planner_threads, since fftw and fftwf have
+/// separate thread state. Starts FFTW's threads the first time more than one
+/// is asked for, and falls back to a single thread if that fails.
+#[cfg(feature = "fftw-threads")]
+pub(crate) fn set_planner_threads(threads: usize,
+                                  init_threads: unsafe extern "C" fn() -> c_int,
+                                  plan_with_nthreads: unsafe extern "C" fn(c_int),
+                                  state: &AtomicUsize) -> usize {
+    if state.load(Ordering::SeqCst) == UNINITIALIZED {
+        // No need to start any threads just to plan single threaded
+        if threads <= 1 {
+            return 1;
+        }
+        let result = if unsafe { init_threads() } != 0 { INITIALIZED } else { FAILED };
+        state.store(result, Ordering::SeqCst);
+    }
+
+    if state.load(Ordering::SeqCst) == FAILED {
+        return 1;
+    }
+
+    let threads = if threads < 1 { 1 } else { threads };
+    unsafe { plan_with_nthreads(threads as c_int) };
+    threads
+}
@@ -1,4 +1,9 @@
+use std::str::FromStr;
+use fftw::float::FftFloat;
+
+
 #[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 /// These can be found in fftw/api/fftw2.h in the FFTW source. In FFTW, they are
 /// defined with #define.
 /// You can read more about planner flags here:
@@ -16,18 +21,91 @@ pub enum PlannerFlags {
 }
 
 
+/// Parses a planner effort, as given to --plan-effort. In order of increasing
+/// planning time: "estimate", "measure", "patient" and "exhaustive". Also
+/// accepts "wisdom-only", which fails to plan any size without saved wisdom.
+impl FromStr for PlannerFlags {
+    type Err = String;
+
+    fn from_str(effort: &str) -> Result<PlannerFlags, String> {
+        match effort {
+            "estimate" => Ok(PlannerFlags::Estimate),
+            "measure" => Ok(PlannerFlags::Measure),
+            "patient" => Ok(PlannerFlags::Patient),
+            "exhaustive" => Ok(PlannerFlags::Exhaustive),
+            "wisdom-only" => Ok(PlannerFlags::WisdomOnly),
+            _ => Err(format!("unknown planner effort \"{}\"", effort)),
+        }
+    }
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The sign of the exponent in a complex transform. FFTW_FORWARD and
+/// FFTW_BACKWARD in fftw3.h.
+pub enum FftwDirection {
+    Forward     = -1,
+    Backward    = 1,
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The kinds of real-to-real transforms. These are the fftw_r2r_kind values
+/// from fftw3.h. See: http://www.fftw.org/doc/Real_002dto_002dReal-Transform-Kinds.html
+pub enum R2RKind {
+    /// Real to halfcomplex (the output format of the old FFTW r2c)
+    R2hc        = 0,
+    /// Halfcomplex to real, the inverse of R2hc
+    Hc2r        = 1,
+    /// Discrete Hartley transform
+    Dht         = 2,
+    /// DCT-I
+    Redft00     = 3,
+    /// DCT-III, the inverse of DCT-II
+    Redft01     = 4,
+    /// DCT-II, usually just called "the" DCT. Used for MFCCs.
+    Redft10     = 5,
+    /// DCT-IV
+    Redft11     = 6,
+    /// DST-I
+    Rodft00     = 7,
+    /// DST-III, the inverse of DST-II
+    Rodft01     = 8,
+    /// DST-II
+    Rodft10     = 9,
+    /// DST-IV
+    Rodft11     = 10,
+}
+
+
 #[repr(C)]
-#[derive(Clone, Copy)]
-/// Represents a 64-bit complex number.
-pub struct FftwComplex {
-    pub re: f64,
-    pub im: f64
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// Represents a complex number. Defaults to 64-bit parts, like fftw_complex;
+/// FftwComplex<f32> matches fftwf_complex.
+pub struct FftwComplex<T = f64> {
+    pub re: T,
+    pub im: T
 }
 
 
-impl FftwComplex {
+impl<T: FftFloat> FftwComplex<T> {
+    /// Get zero as a complex number
+    pub fn zero() -> FftwComplex<T> {
+        FftwComplex{re: T::from_f64(0.0), im: T::from_f64(0.0)}
+    }
+
     /// Get the absolute value (distance from zero) of the complex number
-    pub fn abs(&self) -> f64 {
+    pub fn abs(&self) -> T {
         ((self.re * self.re) + (self.im * self.im)).sqrt()
     }
 }
+
+
+#[test]
+fn test_parse_planner_effort() {
+    assert_eq!("patient".parse(), Ok(PlannerFlags::Patient));
+    assert_eq!("estimate".parse(), Ok(PlannerFlags::Estimate));
+    assert!("quick".parse::<PlannerFlags>().is_err());
+}
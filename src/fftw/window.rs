@@ -0,0 +1,218 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+use fftw::float::{FftFloat, FftReal};
+
+
+/// A window function, applied to each block of samples before the FFT to
+/// reduce spectral leakage. They trade off frequency resolution (how narrow a
+/// tone's peak is) against leakage (how far it smears into other bins).
+/// See: https://en.wikipedia.org/wiki/Window_function
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WindowFunction {
+    /// No window. The narrowest peaks, but the most leakage.
+    Rectangular,
+    /// Raised cosine. A good general purpose window, and the default.
+    Hann,
+    /// Like Hann, but tuned to cancel the nearest side lobe
+    Hamming,
+    /// Three term cosine window with lower leakage than Hann
+    Blackman,
+    /// Four term cosine window with very low leakage (-92dB side lobes)
+    BlackmanHarris,
+    /// Very wide peaks, but reads a tone's amplitude correctly even between
+    /// bins. Good for measuring levels.
+    FlatTop,
+    /// Kaiser window with the given beta. Larger betas lower the leakage and
+    /// widen the peaks; 0 is rectangular, around 8.6 is similar to Blackman.
+    Kaiser(f64),
+}
+
+
+impl WindowFunction {
+    /// The window's value at index out of size points. Windows are symmetric,
+    /// with the ends at index 0 and size-1.
+    pub fn coefficient(&self, index: usize, size: usize) -> f64 {
+        if size < 2 {
+            return 1.0;
+        }
+        // Position in the window, from 0 to 1
+        let x = index as f64 / (size - 1) as f64;
+        match *self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => cosine_sum(&[0.5, 0.5], x),
+            WindowFunction::Hamming => cosine_sum(&[0.54, 0.46], x),
+            WindowFunction::Blackman => cosine_sum(&[0.42, 0.5, 0.08], x),
+            WindowFunction::BlackmanHarris => cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168], x),
+            WindowFunction::FlatTop => cosine_sum(&[0.21557895, 0.41663158, 0.277263158,
+                                                    0.083578947, 0.006947368], x),
+            WindowFunction::Kaiser(beta) => {
+                let r = 2.0 * x - 1.0;
+                bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(beta)
+            },
+        }
+    }
+}
+
+
+/// Parses a window name, as given to --window: "rectangular", "hann",
+/// "hamming", "blackman", "blackman-harris", "flat-top", or "kaiser" with an
+/// optional beta such as "kaiser:6". The beta defaults to 8.6, and must be
+/// finite and not negative.
+impl FromStr for WindowFunction {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<WindowFunction, String> {
+        match name {
+            "rectangular" => Ok(WindowFunction::Rectangular),
+            "hann" | "hanning" => Ok(WindowFunction::Hann),
+            "hamming" => Ok(WindowFunction::Hamming),
+            "blackman" => Ok(WindowFunction::Blackman),
+            "blackman-harris" => Ok(WindowFunction::BlackmanHarris),
+            "flat-top" => Ok(WindowFunction::FlatTop),
+            "kaiser" => Ok(WindowFunction::Kaiser(8.6)),
+            _ if name.starts_with("kaiser:") => {
+                match name["kaiser:".len()..].parse::<f64>() {
+                    Ok(beta) if beta.is_finite() && beta >= 0.0 => Ok(WindowFunction::Kaiser(beta)),
+                    _ => Err(format!("bad Kaiser beta in \"{}\"", name)),
+                }
+            },
+            _ => Err(format!("unknown window \"{}\"", name)),
+        }
+    }
+}
+
+
+/// Precomputes the multipliers for a window function so computing the value
+/// only takes a single multiplication.
+pub struct WindowCalculator<T: FftFloat = FftReal> {
+    window: WindowFunction,
+    multipliers: Vec<T>,
+    coherent_gain: f64,
+    equivalent_noise_bandwidth: f64,
+}
+
+
+impl<T: FftFloat> WindowCalculator<T> {
+    /// Compute the cache of multipliers for a window of fft_size points. They
+    /// and the gains are computed in f64, then the multipliers are rounded to
+    /// T.
+    pub fn new(window: WindowFunction, fft_size: usize) -> WindowCalculator<T> {
+        let coefficients: Vec<f64> = (0..fft_size).map(|i| window.coefficient(i, fft_size)).collect();
+        let sum: f64 = coefficients.iter().sum();
+        let sum_of_squares: f64 = coefficients.iter().map(|w| w * w).sum();
+
+        WindowCalculator {
+            window: window,
+            multipliers: coefficients.iter().map(|&w| T::from_f64(w)).collect(),
+            coherent_gain: sum / fft_size as f64,
+            equivalent_noise_bandwidth: fft_size as f64 * sum_of_squares / (sum * sum),
+        }
+    }
+
+    /// Multiplies the given value against the window multiplier value for
+    /// this index
+    pub fn get_value(&self, index: usize, val: T) -> T {
+        self.multipliers[index] * val
+    }
+
+    /// The window function the multipliers are for
+    pub fn get_window(&self) -> WindowFunction {
+        self.window
+    }
+
+    /// The average of the multipliers. A windowed sine's peak is this much
+    /// smaller than it would be with no window, so divide by it to get the
+    /// amplitude back.
+    pub fn get_coherent_gain(&self) -> f64 {
+        self.coherent_gain
+    }
+
+    /// The equivalent noise bandwidth in bins: how wide a rectangular filter
+    /// would have to be to pass the same amount of white noise as each bin.
+    /// Divide noise power readings by this to get the power per bin width.
+    pub fn get_equivalent_noise_bandwidth(&self) -> f64 {
+        self.equivalent_noise_bandwidth
+    }
+}
+
+
+/// A sum of cosines with alternating signs: a0 - a1*cos(2 pi x) +
+/// a2*cos(4 pi x) - ..., which is what most windows are
+fn cosine_sum(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().enumerate().fold(0.0, |total, (k, &a)| {
+        let term = a * (2.0 * PI * k as f64 * x).cos();
+        if k % 2 == 0 { total + term } else { total - term }
+    })
+}
+
+
+/// The zeroth order modified Bessel function of the first kind, from its power
+/// series. Converges quickly for the betas used in Kaiser windows.
+fn bessel_i0(x: f64) -> f64 {
+    let quarter_x_squared = x * x / 4.0;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-16 {
+        term *= quarter_x_squared / (k * k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+
+#[test]
+fn test_window_gains() {
+    // Published coherent gains and equivalent noise bandwidths
+    let expected = [
+        (WindowFunction::Rectangular, 1.0, 1.0),
+        (WindowFunction::Hann, 0.5, 1.5),
+        (WindowFunction::Hamming, 0.54, 1.36),
+        (WindowFunction::Blackman, 0.42, 1.73),
+        (WindowFunction::BlackmanHarris, 0.359, 2.0),
+        (WindowFunction::FlatTop, 0.216, 3.77),
+        (WindowFunction::Kaiser(0.0), 1.0, 1.0),
+    ];
+    for &(window, gain, enbw) in expected.iter() {
+        let calculator: WindowCalculator<f64> = WindowCalculator::new(window, 4096);
+        assert!((calculator.get_coherent_gain() - gain).abs() < 0.005,
+                "{:?} gain {}", window, calculator.get_coherent_gain());
+        assert!((calculator.get_equivalent_noise_bandwidth() - enbw).abs() < 0.01,
+                "{:?} ENBW {}", window, calculator.get_equivalent_noise_bandwidth());
+    }
+}
+
+
+#[test]
+fn test_window_shapes() {
+    let size = 65;
+    for &window in [WindowFunction::Hann, WindowFunction::Hamming, WindowFunction::Blackman,
+                    WindowFunction::BlackmanHarris, WindowFunction::FlatTop,
+                    WindowFunction::Kaiser(6.0)].iter() {
+        // Symmetric and peaking at 1 in the middle
+        for i in 0..size {
+            assert!((window.coefficient(i, size) - window.coefficient(size - 1 - i, size)).abs() < 1e-12);
+        }
+        assert!((window.coefficient(size / 2, size) - 1.0).abs() < 1e-3, "{:?}", window);
+    }
+    assert!(WindowFunction::Hann.coefficient(0, size).abs() < 1e-12);
+}
+
+
+#[test]
+fn test_parse_window() {
+    assert_eq!("blackman-harris".parse(), Ok(WindowFunction::BlackmanHarris));
+    assert_eq!("kaiser".parse(), Ok(WindowFunction::Kaiser(8.6)));
+    assert_eq!("kaiser:5".parse(), Ok(WindowFunction::Kaiser(5.0)));
+    assert!("kaiser:-1".parse::<WindowFunction>().is_err());
+    assert!("triangle".parse::<WindowFunction>().is_err());
+}
+
+
+#[test]
+fn test_parse_kaiser_non_finite() {
+    assert!("kaiser:inf".parse::<WindowFunction>().is_err());
+    assert!("kaiser:-inf".parse::<WindowFunction>().is_err());
+    assert!("kaiser:NaN".parse::<WindowFunction>().is_err());
+}
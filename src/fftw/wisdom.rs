@@ -0,0 +1,106 @@
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use fftw::float::{FftwFloat, FftReal};
+
+
+/// Errors from loading or saving wisdom
+#[derive(Debug)]
+pub enum WisdomError {
+    /// Neither $XDG_CACHE_HOME nor $HOME is set
+    NoCacheDir,
+    /// The cache directory couldn't be created
+    Io(io::Error),
+    /// The path isn't valid UTF-8 or contains a nul byte
+    InvalidPath,
+    /// FFTW couldn't read or write the wisdom file. It doesn't say why.
+    Fftw,
+}
+
+
+/// FFTW "wisdom" is what the planner learned while measuring plans. Saving it
+/// and loading it on the next start lets FFTW skip straight to the plan it
+/// picked last time, so even PlannerFlags::Patient plans are made instantly.
+/// See: http://www.fftw.org/doc/Words-of-Wisdom_002dSaving-Plans.html
+///
+/// Wisdom is per precision; T picks which planner to load it into.
+pub fn import_from_file<T: FftwFloat>(path: &Path) -> Result<(), WisdomError> {
+    let filename = path_to_cstring(path)?;
+    if unsafe { T::import_wisdom_from_filename(filename.as_ptr()) } == 0 {
+        return Err(WisdomError::Fftw);
+    }
+    Ok(())
+}
+
+
+/// Save all of the wisdom for precision T to a file
+pub fn export_to_file<T: FftwFloat>(path: &Path) -> Result<(), WisdomError> {
+    let filename = path_to_cstring(path)?;
+    if unsafe { T::export_wisdom_to_filename(filename.as_ptr()) } == 0 {
+        return Err(WisdomError::Fftw);
+    }
+    Ok(())
+}
+
+
+/// Load the cached wisdom for the default precision. Fails harmlessly on the
+/// first run, when there is no cache yet.
+pub fn load_cached() -> Result<(), WisdomError> {
+    let path = cache_file::<FftReal>()?;
+    import_from_file::<FftReal>(&path)
+}
+
+
+/// Save the wisdom for the default precision to the cache, creating the cache
+/// directory if needed. Call this after creating the plans so that they're
+/// included.
+pub fn save_cached() -> Result<(), WisdomError> {
+    let path = cache_file::<FftReal>()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(WisdomError::Io)?;
+    }
+    export_to_file::<FftReal>(&path)
+}
+
+
+/// The wisdom cache file for precision T:
+/// $XDG_CACHE_HOME/rusty_bars/wisdom, or ~/.cache/rusty_bars/wisdom
+pub fn cache_file<T: FftwFloat>() -> Result<PathBuf, WisdomError> {
+    let dir = cache_dir(env::var_os("XDG_CACHE_HOME").map(PathBuf::from),
+                        env::var_os("HOME").map(PathBuf::from));
+    match dir {
+        Some(dir) => Ok(dir.join(T::wisdom_file_name())),
+        None => Err(WisdomError::NoCacheDir),
+    }
+}
+
+
+/// Pick the cache directory following the XDG base directory spec. An empty
+/// or relative $XDG_CACHE_HOME is ignored, as the spec requires.
+fn cache_dir(xdg_cache_home: Option<PathBuf>, home: Option<PathBuf>) -> Option<PathBuf> {
+    let base = match xdg_cache_home {
+        Some(ref dir) if dir.is_absolute() => Some(dir.clone()),
+        _ => home.map(|home| home.join(".cache")),
+    };
+    base.map(|base| base.join("rusty_bars"))
+}
+
+
+fn path_to_cstring(path: &Path) -> Result<CString, WisdomError> {
+    let path = path.to_str().ok_or(WisdomError::InvalidPath)?;
+    CString::new(path).map_err(|_| WisdomError::InvalidPath)
+}
+
+
+#[test]
+fn test_cache_dir() {
+    assert_eq!(cache_dir(Some(PathBuf::from("/xdg")), Some(PathBuf::from("/home/u"))),
+               Some(PathBuf::from("/xdg/rusty_bars")));
+    assert_eq!(cache_dir(Some(PathBuf::from("relative")), Some(PathBuf::from("/home/u"))),
+               Some(PathBuf::from("/home/u/.cache/rusty_bars")));
+    assert_eq!(cache_dir(None, Some(PathBuf::from("/home/u"))),
+               Some(PathBuf::from("/home/u/.cache/rusty_bars")));
+    assert_eq!(cache_dir(None, None), None);
+}
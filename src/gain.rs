@@ -0,0 +1,221 @@
+use std::str::FromStr;
+use fftw::audio::DEFAULT_FLOOR;
+use fftw::float::FftFloat;
+
+
+/// How the range of levels shown is picked
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GainMode {
+    /// Always show the same range, up to 0dBFS, so quiet passages look quiet
+    Fixed,
+    /// Automatic gain control: follow the loudest recent level, so quiet
+    /// music still fills the screen. The range never drops below the FFT's
+    /// floor, so silence stays flat.
+    Automatic,
+}
+
+
+/// Parses "fixed" or "auto"
+impl FromStr for GainMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<GainMode, String> {
+        match name {
+            "fixed" => Ok(GainMode::Fixed),
+            "auto" => Ok(GainMode::Automatic),
+            _ => Err(format!("unknown gain mode \"{}\"", name)),
+        }
+    }
+}
+
+
+/// How levels are scaled to the screen, and when to treat the input as
+/// silence
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GainSettings {
+    pub mode: GainMode,
+    /// How many dB the screen shows, from an empty column to a full one
+    pub range: f64,
+    /// How fast the automatic gain's peak falls in dB/s, so the screen fills
+    /// up again after something loud
+    pub decay: f64,
+    /// Frames whose loudest level is below this, in dBFS, are silence. None
+    /// turns the gate off.
+    pub gate: Option<f64>,
+    /// How long in seconds the input has to stay below the gate before it
+    /// closes, so short pauses don't flicker
+    pub gate_hold: f64,
+}
+
+
+impl Default for GainSettings {
+    fn default() -> GainSettings {
+        GainSettings {
+            mode: GainMode::Fixed,
+            range: 80.0,
+            decay: 6.0,
+            gate: None,
+            gate_hold: 0.25,
+        }
+    }
+}
+
+
+/// Picks the range of levels to show, and gates out silence
+pub struct GainControl {
+    settings: GainSettings,
+    /// The loudest recent level for automatic gain, in dBFS
+    peak: Option<f64>,
+    /// The quietest level the FFT reports, in dBFS
+    floor: f64,
+    /// Whether there's sound. Always true without a gate.
+    gate_open: bool,
+    /// How long the input has been below the gate, in seconds
+    quiet_time: f64,
+}
+
+
+impl GainControl {
+    pub fn new(settings: GainSettings) -> GainControl {
+        GainControl {
+            settings: settings,
+            peak: None,
+            floor: DEFAULT_FLOOR,
+            gate_open: settings.gate.is_none(),
+            quiet_time: 0.0,
+        }
+    }
+
+    pub fn get_settings(&self) -> GainSettings {
+        self.settings
+    }
+
+    /// Set the quietest level the FFT reports, as given to
+    /// AudioFft::set_floor. The automatic gain stops falling once the floor
+    /// is at the bottom of the range.
+    pub fn set_floor(&mut self, floor: f64) {
+        self.floor = floor;
+    }
+
+    /// Follow a frame of levels in dBFS, elapsed seconds after the last
+    pub fn update<T: FftFloat>(&mut self, frame: &[T], elapsed: f64) {
+        let loudest = frame.iter().fold(f64::NEG_INFINITY, |max, x| max.max(x.to_f64()));
+
+        if let Some(threshold) = self.settings.gate {
+            if loudest >= threshold {
+                self.gate_open = true;
+                self.quiet_time = 0.0;
+            } else {
+                self.quiet_time += elapsed;
+                if self.quiet_time >= self.settings.gate_hold {
+                    self.gate_open = false;
+                }
+            }
+        }
+
+        // Hold the gain through silence, so the music comes back at the same
+        // size rather than noise being turned up to fill the screen. Without
+        // a gate, the floor keeps the gain from chasing silence or hiss all
+        // the way down.
+        if self.gate_open {
+            let decayed = self.peak.map_or(loudest, |peak| peak - self.settings.decay * elapsed);
+            self.peak = Some(loudest.max(decayed).max(self.floor + self.settings.range));
+        }
+    }
+
+    /// Whether the last frames had sound in them. Always true without a gate.
+    pub fn is_open(&self) -> bool {
+        self.gate_open
+    }
+
+    /// The levels in dBFS of an empty column and a full one
+    pub fn get_range(&self) -> (f64, f64) {
+        let top = match self.settings.mode {
+            GainMode::Fixed => 0.0,
+            GainMode::Automatic => self.peak.unwrap_or(0.0),
+        };
+        (top - self.settings.range, top)
+    }
+}
+
+
+/// Feed a constant level to a gain control for duration seconds, in steps
+#[cfg(test)]
+fn run_gain(gain: &mut GainControl, level: f64, duration: f64, step: f64) {
+    let steps = (duration / step).round() as usize;
+    for _ in 0..steps {
+        gain.update(&[level - 10.0, level], step);
+    }
+}
+
+
+#[test]
+fn test_fixed_range() {
+    let mut gain = GainControl::new(GainSettings::default());
+    assert_eq!(gain.get_range(), (-80.0, 0.0));
+    run_gain(&mut gain, -50.0, 1.0, 0.01);
+    assert_eq!(gain.get_range(), (-80.0, 0.0));
+    assert!(gain.is_open());
+}
+
+
+#[test]
+fn test_automatic_gain() {
+    let settings = GainSettings { mode: GainMode::Automatic, range: 60.0, decay: 10.0, ..GainSettings::default() };
+    for &step in [0.001, 0.05].iter() {
+        let mut gain = GainControl::new(settings);
+        // Jumps up to the loudest level straight away
+        run_gain(&mut gain, -30.0, 0.1, step);
+        assert_eq!(gain.get_range(), (-90.0, -30.0));
+        run_gain(&mut gain, -10.0, 0.1, step);
+        assert_eq!(gain.get_range(), (-70.0, -10.0));
+        // Then decays slowly when things get quieter
+        run_gain(&mut gain, -40.0, 1.5, step);
+        let (bottom, top) = gain.get_range();
+        assert!((top + 25.0).abs() < 1e-6 && (bottom + 85.0).abs() < 1e-6, "{} {}", bottom, top);
+    }
+}
+
+
+#[test]
+fn test_noise_gate() {
+    let settings = GainSettings { mode: GainMode::Automatic, gate: Some(-70.0), gate_hold: 0.2,
+                                  ..GainSettings::default() };
+    let mut gain = GainControl::new(settings);
+    assert!(!gain.is_open());
+    run_gain(&mut gain, -90.0, 1.0, 0.01);
+    assert!(!gain.is_open());
+    assert_eq!(gain.get_range(), (-80.0, 0.0));
+
+    run_gain(&mut gain, -20.0, 0.01, 0.01);
+    assert!(gain.is_open());
+    // Short pauses keep the gate open
+    run_gain(&mut gain, -90.0, 0.15, 0.01);
+    assert!(gain.is_open());
+    run_gain(&mut gain, -90.0, 0.1, 0.01);
+    assert!(!gain.is_open());
+
+    // The gain doesn't chase the noise while the gate is closed
+    let range = gain.get_range();
+    run_gain(&mut gain, -90.0, 5.0, 0.01);
+    assert_eq!(gain.get_range(), range);
+}
+
+
+#[test]
+fn test_automatic_gain_stops_at_floor() {
+    let settings = GainSettings { mode: GainMode::Automatic, ..GainSettings::default() };
+    let mut gain = GainControl::new(settings);
+    // Digital silence puts every bin at the floor, which stays at the bottom
+    for _ in 0..1000 {
+        gain.update(&[DEFAULT_FLOOR; 16], 0.01);
+    }
+    assert_eq!(gain.get_range(), (DEFAULT_FLOOR, DEFAULT_FLOOR + 80.0));
+
+    // After something loud, the gain falls back until it gets there
+    let mut gain = GainControl::new(GainSettings { decay: 20.0, ..settings });
+    gain.set_floor(-100.0);
+    run_gain(&mut gain, -5.0, 0.1, 0.01);
+    run_gain(&mut gain, -100.0, 10.0, 0.01);
+    assert_eq!(gain.get_range(), (-100.0, -20.0));
+}
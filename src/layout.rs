@@ -0,0 +1,392 @@
+use std::str::FromStr;
+use visualizer::{draw_cell, BarStyle};
+
+
+/// Which way bars grow from where they start
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+
+/// The heights of one channel's bars and their peak markers, in steps of
+/// the bar style's resolution
+pub struct ChannelBars {
+    pub heights: Vec<usize>,
+    pub peaks: Option<Vec<usize>>,
+}
+
+
+/// A way of arranging the bars on the screen. The visualizer asks the layout
+/// how much room each channel's bars have, turns the levels into bars that
+/// fit, and has the layout draw them.
+pub trait Layout {
+    /// How many channels the layout shows: 1 for the combined spectrum, or 2
+    /// for left and right
+    fn get_channel_count(&self) -> usize {
+        1
+    }
+
+    /// The columns and rows each channel's bars have on a screen of width by
+    /// height cells
+    fn get_bar_area(&self, width: usize, height: usize) -> (usize, usize);
+
+    /// Draw the bars into rows, the lines of the screen counting up from the
+    /// bottom. There are get_channel_count channels, each sized for
+    /// get_bar_area. Every cell of rows gets drawn.
+    fn fill_rows(&self, style: BarStyle, channels: &[ChannelBars], rows: &mut [Vec<char>]);
+
+    /// How far along the frequencies and how far up its bar the cell at
+    /// column x and row y is, from 0 to 1, for coloring. By default, bars
+    /// run left to right and grow up from the bottom of the screen.
+    fn get_bar_position(&self, x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+        ((x as f64 + 0.5) / width as f64, (y as f64 + 0.5) / height as f64)
+    }
+}
+
+
+/// The part of the screen one channel's bars are drawn into
+#[derive(Clone, Copy, Debug)]
+struct Region {
+    /// The first column
+    x: usize,
+    /// How many columns wide it is
+    columns: usize,
+    /// The row the bars start from
+    base: usize,
+    /// How many rows the bars can grow through
+    row_count: usize,
+    /// Which way the bars grow from base
+    direction: Direction,
+    /// Put the first bar on the right instead of the left
+    mirrored: bool,
+}
+
+
+/// Draw a channel's bars into a region of rows
+fn fill_region(style: BarStyle, bars: &ChannelBars, rows: &mut [Vec<char>], region: Region) {
+    let Region { x, columns, base, row_count, direction, mirrored } = region;
+    let reverse = |values: &[usize]| -> Vec<usize> {
+        if mirrored { values.iter().rev().cloned().collect() } else { values.to_vec() }
+    };
+    let heights = reverse(&bars.heights);
+    let peaks = bars.peaks.as_ref().map(|peaks| reverse(peaks));
+    // Mirrored bars line up against the right edge
+    let bars_per_column = style.get_resolution().0;
+    let offset = if mirrored { columns.saturating_sub(heights.len() / bars_per_column) } else { 0 };
+
+    for row in 0..row_count {
+        let y = match direction {
+            Direction::Up => base + row,
+            Direction::Down => base - row,
+        };
+        for column in 0..columns {
+            rows[y][x + column] = if column < offset {
+                ' '
+            } else {
+                draw_cell(style, &heights, peaks.as_ref().map(|peaks| &peaks[..]), column - offset, row, direction)
+            };
+        }
+    }
+}
+
+
+/// Bars grow up from the bottom of the screen
+pub struct BottomLayout;
+
+
+impl Layout for BottomLayout {
+    fn get_bar_area(&self, width: usize, height: usize) -> (usize, usize) {
+        (width, height)
+    }
+
+    fn fill_rows(&self, style: BarStyle, channels: &[ChannelBars], rows: &mut [Vec<char>]) {
+        let (width, height) = (rows.first().map_or(0, |row| row.len()), rows.len());
+        let region = Region {
+            x: 0,
+            columns: width,
+            base: 0,
+            row_count: height,
+            direction: Direction::Up,
+            mirrored: false,
+        };
+        fill_region(style, &channels[0], rows, region);
+    }
+}
+
+
+/// Bars hang down from the top of the screen
+pub struct InvertedLayout;
+
+
+impl Layout for InvertedLayout {
+    fn get_bar_area(&self, width: usize, height: usize) -> (usize, usize) {
+        (width, height)
+    }
+
+    fn fill_rows(&self, style: BarStyle, channels: &[ChannelBars], rows: &mut [Vec<char>]) {
+        let (width, height) = (rows.first().map_or(0, |row| row.len()), rows.len());
+        if height > 0 {
+            let region = Region {
+                x: 0,
+                columns: width,
+                base: height - 1,
+                row_count: height,
+                direction: Direction::Down,
+                mirrored: false,
+            };
+            fill_region(style, &channels[0], rows, region);
+        }
+    }
+
+    fn get_bar_position(&self, x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+        ((x as f64 + 0.5) / width as f64, (height as f64 - y as f64 - 0.5) / height as f64)
+    }
+}
+
+
+/// The rows above and below the middle of the screen. With an odd height,
+/// the middle row goes with the top half.
+fn split_rows(height: usize) -> (usize, usize) {
+    (height - height / 2, height / 2)
+}
+
+
+/// Fill the top half with bars growing up from the middle, and the bottom
+/// half with bars growing down from it
+fn fill_halves(style: BarStyle, top: &ChannelBars, bottom: &ChannelBars, rows: &mut [Vec<char>]) {
+    let width = rows.first().map_or(0, |row| row.len());
+    let (upper, lower) = split_rows(rows.len());
+    let region = Region {
+        x: 0,
+        columns: width,
+        base: lower,
+        row_count: upper,
+        direction: Direction::Up,
+        mirrored: false,
+    };
+    fill_region(style, top, rows, region);
+    if lower > 0 {
+        let region = Region {
+            x: 0,
+            columns: width,
+            base: lower - 1,
+            row_count: lower,
+            direction: Direction::Down,
+            mirrored: false,
+        };
+        fill_region(style, bottom, rows, region);
+    }
+}
+
+
+/// How far a row is from the middle of the screen, for layouts split by
+/// fill_halves
+fn half_position(y: usize, height: usize) -> f64 {
+    let (upper, lower) = split_rows(height);
+    let row = if y >= lower { y - lower } else { lower - 1 - y };
+    (row as f64 + 0.5) / upper as f64
+}
+
+
+/// Bars grow both up and down from the middle of the screen
+pub struct CenteredLayout;
+
+
+impl Layout for CenteredLayout {
+    fn get_bar_area(&self, width: usize, height: usize) -> (usize, usize) {
+        (width, split_rows(height).0)
+    }
+
+    fn fill_rows(&self, style: BarStyle, channels: &[ChannelBars], rows: &mut [Vec<char>]) {
+        fill_halves(style, &channels[0], &channels[0], rows);
+    }
+
+    fn get_bar_position(&self, x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+        ((x as f64 + 0.5) / width as f64, half_position(y, height))
+    }
+}
+
+
+/// The left channel grows up from the middle of the screen, and the right
+/// channel down from it
+pub struct StereoSplitLayout;
+
+
+impl Layout for StereoSplitLayout {
+    fn get_channel_count(&self) -> usize {
+        2
+    }
+
+    fn get_bar_area(&self, width: usize, height: usize) -> (usize, usize) {
+        (width, split_rows(height).0)
+    }
+
+    fn fill_rows(&self, style: BarStyle, channels: &[ChannelBars], rows: &mut [Vec<char>]) {
+        fill_halves(style, &channels[0], &channels[1], rows);
+    }
+
+    fn get_bar_position(&self, x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+        ((x as f64 + 0.5) / width as f64, half_position(y, height))
+    }
+}
+
+
+/// The left channel on the left half of the screen and the right channel on
+/// the right, mirrored so the low frequencies meet in the middle
+pub struct StereoMirrorLayout;
+
+
+impl Layout for StereoMirrorLayout {
+    fn get_channel_count(&self) -> usize {
+        2
+    }
+
+    fn get_bar_area(&self, width: usize, height: usize) -> (usize, usize) {
+        (width / 2, height)
+    }
+
+    fn fill_rows(&self, style: BarStyle, channels: &[ChannelBars], rows: &mut [Vec<char>]) {
+        let (width, height) = (rows.first().map_or(0, |row| row.len()), rows.len());
+        let left = width / 2;
+        let region = Region {
+            x: 0,
+            columns: left,
+            base: 0,
+            row_count: height,
+            direction: Direction::Up,
+            mirrored: true,
+        };
+        fill_region(style, &channels[0], rows, region);
+        let region = Region {
+            x: left,
+            columns: width - left,
+            base: 0,
+            row_count: height,
+            direction: Direction::Up,
+            mirrored: false,
+        };
+        fill_region(style, &channels[1], rows, region);
+    }
+
+    fn get_bar_position(&self, x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+        let left = width / 2;
+        let across = if x < left {
+            ((left - x) as f64 - 0.5) / left as f64
+        } else {
+            ((x - left) as f64 + 0.5) / (width - left) as f64
+        };
+        (across, (y as f64 + 0.5) / height as f64)
+    }
+}
+
+
+/// The built in layouts, as picked on the command line
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BarLayout {
+    Bottom,
+    Inverted,
+    Centered,
+    StereoSplit,
+    StereoMirror,
+}
+
+
+/// Parses "bottom", "inverted", "centered", "stereo" or "mirror"
+impl FromStr for BarLayout {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<BarLayout, String> {
+        match name {
+            "bottom" => Ok(BarLayout::Bottom),
+            "inverted" => Ok(BarLayout::Inverted),
+            "centered" => Ok(BarLayout::Centered),
+            "stereo" => Ok(BarLayout::StereoSplit),
+            "mirror" => Ok(BarLayout::StereoMirror),
+            _ => Err(format!("unknown layout \"{}\"", name)),
+        }
+    }
+}
+
+
+impl BarLayout {
+    /// Make the layout
+    pub fn create(&self) -> Box<dyn Layout> {
+        match *self {
+            BarLayout::Bottom => Box::new(BottomLayout),
+            BarLayout::Inverted => Box::new(InvertedLayout),
+            BarLayout::Centered => Box::new(CenteredLayout),
+            BarLayout::StereoSplit => Box::new(StereoSplitLayout),
+            BarLayout::StereoMirror => Box::new(StereoMirrorLayout),
+        }
+    }
+}
+
+
+/// Draw bars of the given heights with a layout on a small ASCII screen, and
+/// return its lines from the top down
+#[cfg(test)]
+fn draw_layout(layout: BarLayout, channels: &[&[usize]], width: usize, height: usize) -> Vec<String> {
+    let channels: Vec<ChannelBars> = channels.iter()
+        .map(|heights| ChannelBars { heights: heights.to_vec(), peaks: None })
+        .collect();
+    let mut rows = vec![vec!['#'; width]; height];
+    layout.create().fill_rows(BarStyle::Ascii, &channels, &mut rows);
+    rows.iter().rev().map(|row| row.iter().collect()).collect()
+}
+
+
+#[test]
+fn test_single_channel_layouts() {
+    let heights: &[usize] = &[1, 3, 2];
+    assert_eq!(draw_layout(BarLayout::Bottom, &[heights], 3, 3), vec![" | ", " ||", "|||"]);
+    assert_eq!(draw_layout(BarLayout::Inverted, &[heights], 3, 3), vec!["|||", " ||", " | "]);
+    // The top half gets the middle row
+    assert_eq!(draw_layout(BarLayout::Centered, &[&[1, 3, 2]], 3, 5),
+               vec![" | ", " ||", "|||", "|||", " ||"]);
+    assert_eq!(BarLayout::Centered.create().get_bar_area(3, 5), (3, 3));
+}
+
+
+#[test]
+fn test_stereo_layouts() {
+    let (left, right): (&[usize], &[usize]) = (&[2, 1], &[0, 1, 1]);
+    assert_eq!(draw_layout(BarLayout::StereoSplit, &[left, right], 3, 4), vec!["|  ", "|| ", " ||", "   "]);
+    // Low frequencies meet in the middle, with an odd column left over on
+    // the right
+    let layout = BarLayout::StereoMirror.create();
+    assert_eq!(layout.get_channel_count(), 2);
+    assert_eq!(layout.get_bar_area(5, 2), (2, 2));
+    assert_eq!(draw_layout(BarLayout::StereoMirror, &[left, &[2, 0]], 5, 2), vec![" ||  ", "|||  "]);
+}
+
+
+#[test]
+fn test_hanging_bars() {
+    // Bars growing down fill cells from the top, with peak markers below
+    let mut rows = vec![vec!['#'; 2]; 3];
+    InvertedLayout.fill_rows(BarStyle::Blocks, &[ChannelBars { heights: vec![12], peaks: Some(vec![17]) }], &mut rows[..]);
+    let column: Vec<char> = rows.iter().rev().map(|row| row[0]).collect();
+    assert_eq!(column, vec!['\u{2588}', '\u{2580}', '\u{2581}']);
+
+    let bars = ChannelBars { heights: vec![4, 1], peaks: Some(vec![4, 3]) };
+    let mut rows = vec![vec!['#'; 2]; 1];
+    InvertedLayout.fill_rows(BarStyle::Braille, &[bars], &mut rows[..]);
+    // The left bar is full, and the right bar has its top dot and its peak
+    // on the third dot down. The second column has no bars.
+    assert_eq!(rows[0], vec!['\u{286f}', ' ']);
+}
+
+
+#[test]
+fn test_bar_positions() {
+    // Coloring by height follows the bars away from where they start
+    let up = |layout: BarLayout| -> Vec<f64> { (0..4).map(|y| layout.create().get_bar_position(0, y, 4, 4).1).collect() };
+    assert_eq!(up(BarLayout::Bottom), vec![0.125, 0.375, 0.625, 0.875]);
+    assert_eq!(up(BarLayout::Inverted), vec![0.875, 0.625, 0.375, 0.125]);
+    assert_eq!(up(BarLayout::Centered), vec![0.75, 0.25, 0.25, 0.75]);
+    // And by frequency from the middle out when mirrored
+    let across: Vec<f64> = (0..4).map(|x| StereoMirrorLayout.get_bar_position(x, 0, 4, 4).0).collect();
+    assert_eq!(across, vec![0.75, 0.25, 0.25, 0.75]);
+}
@@ -1,5 +1,15 @@
 pub mod ncurses;
 pub mod pulse;
 pub mod visualizer;
+pub mod bands;
+pub mod layout;
+pub mod smoothing;
+pub mod gain;
+pub mod renderer;
+pub mod scope;
+pub mod theme;
+pub mod waterfall;
 pub mod fftw;
 pub mod viz_runner;
+#[cfg(test)]
+mod test_signal;
@@ -1,12 +1,328 @@
 extern crate libc;
 extern crate rusty_bars;
 
+use std::env;
+use std::fmt::Display;
+use std::process;
+use std::str::FromStr;
+
+use rusty_bars::bands::{Aggregation, BandScale};
+use rusty_bars::fftw::audio::ChannelMix;
+use rusty_bars::fftw::backend::FftError;
+use rusty_bars::fftw::types::PlannerFlags;
+use rusty_bars::fftw::window::WindowFunction;
+use rusty_bars::gain::GainMode;
+use rusty_bars::layout::BarLayout;
+#[cfg(feature = "fftw")]
+use rusty_bars::fftw::wisdom;
 use rusty_bars::pulse::PulseAudioMainloop;
-use rusty_bars::viz_runner::VizRunner;
+use rusty_bars::smoothing::SmoothingSettings;
+use rusty_bars::theme::{ColorBy, Theme};
+use rusty_bars::visualizer::{BarStyle, View};
+use rusty_bars::viz_runner::{SAMPLE_RATE, VizOptions, VizRunner};
+use rusty_bars::waterfall::Orientation;
+
+
+const USAGE: &'static str = "\
+Usage: rusty_bars [OPTIONS]
+
+Options:
+    --plan-effort EFFORT   How long FFTW spends finding a fast FFT: estimate,
+                           measure (the default), patient or exhaustive. The
+                           result is cached, so only the first start is slow.
+    --fft-size SAMPLES     Samples per FFT (default 1024). Any size from 2 up
+                           works with FFTW, e.g. 4800 for 10Hz bins at 48kHz;
+                           the pure Rust FFT needs a power of two.
+    --hop-size SAMPLES     Samples between FFTs (default half the FFT size).
+                           Smaller hops overlap the FFTs for more frames per
+                           second.
+    --window WINDOW        Window applied before each FFT: hann (the default),
+                           hamming, blackman, blackman-harris, flat-top,
+                           rectangular, or kaiser with an optional beta such
+                           as kaiser:6.
+    --floor DB             The quietest level the FFT reports, in dBFS
+                           (default -120). A full scale sine is 0dBFS.
+    --gain MODE            fixed (the default) shows levels up to 0dBFS; auto
+                           follows the loudest recent level
+    --range DB             How many dB the bars show (default 80)
+    --agc-decay DB         How fast auto gain recovers after something loud,
+                           in dB/s (default 6)
+    --gate DB              Show flat bars while the input stays below this
+                           level in dBFS, e.g. -70 (default off)
+    --channel-mix MIX      How the channels are combined: max (the loudest
+                           channel, the default), sum or average (of their
+                           power).
+    --style STYLE          Draw bars with blocks (the default, in eighths of a
+                           row), braille (two bars per column, in quarters of
+                           a row) or ascii. Unicode styles need a UTF-8
+                           locale, and fall back to ascii without one.
+    --layout LAYOUT        Where the bars go: bottom (the default), inverted
+                           to hang from the top, centered to grow up and down
+                           from the middle, stereo for the left channel up
+                           and the right channel down, or mirror for left and
+                           right side by side with the bass in the middle
+    --view VIEW            What to show: bars for the spectrum (the default),
+                           scope for each channel's waveform, xy for a
+                           goniometer of left against right, or waterfall
+                           for a scrolling spectrogram
+    --waterfall-range DB:DB
+                           The levels the waterfall shades from blank to
+                           solid, such as -90:-20 (default the gain's range)
+    --history FRAMES       FFT frames the waterfall covers, squeezed or
+                           stretched to fit (default one per line)
+    --orientation WHICH    vertical: the waterfall scrolls down (the default);
+                           horizontal: it scrolls left, with low frequencies
+                           at the bottom
+    --theme THEME          Bar colors: classic (green to yellow to red, the
+                           default), rainbow, fire, ocean or mono
+    --color-by WHAT        Color by height (the default) or frequency
+    --bands SCALE          How frequencies are spread across the screen: log
+                           (the default), linear, octave, third-octave, mel
+                           or bark.
+    --aggregate HOW        How the FFT bins in a band are combined: max (the
+                           default), mean or rms.
+    --attack MS            How long rising bars take to get 63% of the way to
+                           a louder level (default 10)
+    --release MS           The same for falling bars (default 150)
+    --gravity DB           Make falling bars drop like a ball instead, with
+                           this acceleration in dB/s² (default off)
+    --peak-hold MS         Show a marker at each bar's peak, which stays this
+                           long before dropping (default off)
+    --no-smoothing         Draw each frame as it is
+    --min-freq HZ          The lowest frequency shown (default 20)
+    --max-freq HZ          The highest frequency shown (default 20000)
+    -h, --help             Show this message";
+
+
+/// Print an error and the usage, then exit
+fn usage_error(message: &str) -> ! {
+    eprintln!("rusty_bars: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+
+/// Parse an option's value, exiting with the usage if it's missing or bad
+fn parse_value<T, I>(name: &str, value: Option<String>, args: &mut I) -> T
+    where T: FromStr, T::Err: Display, I: Iterator<Item=String> {
+    let value = match value.or_else(|| args.next()) {
+        Some(value) => value,
+        None => usage_error(&format!("{} needs a value", name)),
+    };
+    match value.parse::<T>() {
+        Ok(value) => value,
+        Err(message) => usage_error(&format!("bad value for {}: {}", name, message)),
+    }
+}
+
+
+/// Parse a time in milliseconds into seconds
+fn parse_milliseconds<I: Iterator<Item=String>>(name: &str, value: Option<String>, args: &mut I) -> f64 {
+    let milliseconds = parse_value::<f64, _>(name, value, args);
+    if !(milliseconds >= 0.0) {
+        usage_error(&format!("{} can't be negative", name));
+    }
+    milliseconds / 1000.0
+}
+
+
+/// Parse a range of levels given as "BOTTOM:TOP"
+fn parse_range<I: Iterator<Item=String>>(name: &str, value: Option<String>, args: &mut I) -> (f64, f64) {
+    let value = parse_value::<String, _>(name, value, args);
+    let (bottom, top) = match value.find(':') {
+        Some(index) => (value[..index].parse::<f64>(), value[index + 1..].parse::<f64>()),
+        None => usage_error(&format!("{} needs two levels, like -90:-20", name)),
+    };
+    match (bottom, top) {
+        (Ok(bottom), Ok(top)) if bottom < top => (bottom, top),
+        (Ok(_), Ok(_)) => usage_error(&format!("the bottom of {} must be below the top", name)),
+        _ => usage_error(&format!("bad value for {}: {}", name, value)),
+    }
+}
+
+
+/// Parse the command line arguments into VizOptions
+fn parse_args<I: Iterator<Item=String>>(mut args: I) -> VizOptions {
+    let mut options = VizOptions::default();
+    while let Some(arg) = args.next() {
+        // Accept both "--plan-effort patient" and "--plan-effort=patient"
+        let (name, value) = match arg.find('=') {
+            Some(index) => (arg[..index].to_string(), Some(arg[index + 1..].to_string())),
+            None => (arg.clone(), None),
+        };
+        match &name[..] {
+            "--plan-effort" => {
+                options.plan_effort = parse_value::<PlannerFlags, _>(&name, value, &mut args);
+            },
+            "--fft-size" => {
+                let fft_size = parse_value::<usize, _>(&name, value, &mut args);
+                if fft_size < 2 {
+                    usage_error("--fft-size must be at least 2");
+                }
+                options.fft_size = fft_size;
+            },
+            "--hop-size" => {
+                let hop_size = parse_value::<usize, _>(&name, value, &mut args);
+                if hop_size == 0 {
+                    usage_error("--hop-size must be at least 1");
+                }
+                options.hop_size = Some(hop_size);
+            },
+            "--window" => {
+                options.window = parse_value::<WindowFunction, _>(&name, value, &mut args);
+            },
+            "--floor" => {
+                let floor = parse_value::<f64, _>(&name, value, &mut args);
+                if !(floor < 0.0) {
+                    usage_error("--floor must be below 0dBFS");
+                }
+                options.floor = floor;
+            },
+            "--gain" => {
+                options.gain.mode = parse_value::<GainMode, _>(&name, value, &mut args);
+            },
+            "--range" => {
+                let range = parse_value::<f64, _>(&name, value, &mut args);
+                if !(range > 0.0) {
+                    usage_error("--range must be above 0");
+                }
+                options.gain.range = range;
+            },
+            "--agc-decay" => {
+                let decay = parse_value::<f64, _>(&name, value, &mut args);
+                if !(decay >= 0.0) {
+                    usage_error("--agc-decay can't be negative");
+                }
+                options.gain.decay = decay;
+            },
+            "--gate" => {
+                options.gain.gate = Some(parse_value::<f64, _>(&name, value, &mut args));
+            },
+            "--channel-mix" => {
+                options.channel_mix = parse_value::<ChannelMix, _>(&name, value, &mut args);
+            },
+            "--style" => {
+                options.bar_style = parse_value::<BarStyle, _>(&name, value, &mut args);
+            },
+            "--layout" => {
+                options.layout = parse_value::<BarLayout, _>(&name, value, &mut args);
+            },
+            "--view" => {
+                options.view = parse_value::<View, _>(&name, value, &mut args);
+            },
+            "--waterfall-range" => {
+                options.waterfall.range = Some(parse_range(&name, value, &mut args));
+            },
+            "--history" => {
+                options.waterfall.history = parse_value::<usize, _>(&name, value, &mut args);
+            },
+            "--orientation" => {
+                options.waterfall.orientation = parse_value::<Orientation, _>(&name, value, &mut args);
+            },
+            "--theme" => {
+                options.theme = parse_value::<Theme, _>(&name, value, &mut args);
+            },
+            "--color-by" => {
+                options.color_by = parse_value::<ColorBy, _>(&name, value, &mut args);
+            },
+            "--bands" => {
+                options.bands.scale = parse_value::<BandScale, _>(&name, value, &mut args);
+            },
+            "--aggregate" => {
+                options.bands.aggregation = parse_value::<Aggregation, _>(&name, value, &mut args);
+            },
+            "--attack" => {
+                options.smoothing.attack = parse_milliseconds(&name, value, &mut args);
+            },
+            "--release" => {
+                options.smoothing.release = parse_milliseconds(&name, value, &mut args);
+            },
+            "--gravity" => {
+                let gravity = parse_value::<f64, _>(&name, value, &mut args);
+                if !(gravity >= 0.0) {
+                    usage_error("--gravity can't be negative");
+                }
+                options.smoothing.gravity = gravity;
+            },
+            "--peak-hold" => {
+                options.smoothing.peak_hold = parse_milliseconds(&name, value, &mut args);
+            },
+            "--no-smoothing" => {
+                options.smoothing = SmoothingSettings::none();
+            },
+            "--min-freq" => {
+                options.bands.min_freq = parse_value::<f64, _>(&name, value, &mut args);
+            },
+            "--max-freq" => {
+                options.bands.max_freq = parse_value::<f64, _>(&name, value, &mut args);
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            _ => usage_error(&format!("unknown argument \"{}\"", arg)),
+        }
+    }
+    if !(options.bands.min_freq < options.bands.max_freq) {
+        usage_error("--min-freq must be below --max-freq");
+    }
+    let nyquist = SAMPLE_RATE as f64 / 2.0;
+    if !(options.bands.min_freq < nyquist) {
+        usage_error(&format!("--min-freq must be below {}Hz, half the sample rate", nyquist));
+    }
+    if !(options.bands.max_freq > 0.0) {
+        usage_error("--max-freq must be above 0");
+    }
+    if !(options.bands.min_freq > 0.0) && options.bands.scale != BandScale::Linear {
+        usage_error("--min-freq must be above 0 unless --bands is linear");
+    }
+    options
+}
+
+
+/// Load FFTW's wisdom from the last run. There's none on the first run, which
+/// is fine.
+#[cfg(feature = "fftw")]
+fn load_wisdom() {
+    let _ = wisdom::load_cached();
+}
+
+
+/// Save what FFTW's planner learned for the next start. ncurses owns the
+/// terminal by the time this is called, so failures are silent.
+#[cfg(feature = "fftw")]
+fn save_wisdom() {
+    let _ = wisdom::save_cached();
+}
+
+
+/// The pure Rust FFT doesn't plan, so it has no wisdom
+#[cfg(not(feature = "fftw"))]
+fn load_wisdom() {}
+
+
+#[cfg(not(feature = "fftw"))]
+fn save_wisdom() {}
+
 
 /// Start the visualizer for your default PulseAudio output.
 fn main() {
+    let options = parse_args(env::args().skip(1));
+
+    load_wisdom();
     let mainloop = PulseAudioMainloop::new();
-    VizRunner::new(&mainloop);
+    // The plans are made here, so save the wisdom right after
+    match VizRunner::new_with_options(&mainloop, options) {
+        Ok(_) => {},
+        Err(FftError::UnsupportedSize(size)) => {
+            eprintln!("rusty_bars: this build's FFT doesn't support {} samples", size);
+            process::exit(1);
+        },
+        Err(FftError::PlanFailed) => {
+            eprintln!("rusty_bars: FFTW couldn't plan the FFT; wisdom-only needs wisdom saved for this size");
+            process::exit(1);
+        }
+    }
+    save_wisdom();
+
     mainloop.run();
 }
@@ -1,5 +1,7 @@
 extern crate libc;
-use self::libc::{c_int, c_char};
+use self::libc::{c_int, c_char, c_short};
+#[cfg(feature = "ncursesw")]
+use self::libc::wchar_t;
 
 /// Module for external ncurses functions and types
 
@@ -7,7 +9,8 @@ use self::libc::{c_int, c_char};
 pub enum Window {}
 
 
-#[link(name="ncurses")]
+#[cfg_attr(feature = "ncursesw", link(name="ncursesw"))]
+#[cfg_attr(not(feature = "ncursesw"), link(name="ncurses"))]
 extern {
     pub fn initscr() -> *mut Window;
     pub fn endwin() -> c_int;
@@ -17,4 +20,33 @@ extern {
     pub fn getmaxy(win: *mut Window) -> c_int;
     pub fn getmaxx(win: *mut Window) -> c_int;
     pub fn curs_set(visibility: c_int) -> c_int;
+    pub fn has_colors() -> bool;
+    pub fn start_color() -> c_int;
+    pub fn use_default_colors() -> c_int;
+    pub fn init_pair(pair: c_short, f: c_short, b: c_short) -> c_int;
+    pub fn wattron(win: *mut Window, attrs: c_int) -> c_int;
+    pub fn wattroff(win: *mut Window, attrs: c_int) -> c_int;
+    /// The number of colors the terminal has, after start_color
+    pub static COLORS: c_int;
 }
+
+
+// Wide character functions, which are only in libncursesw
+#[cfg(feature = "ncursesw")]
+#[link(name="ncursesw")]
+extern "C" {
+    pub fn mvwaddnwstr(win: *mut Window, y: c_int, x: c_int, text: *const wchar_t, n: c_int) -> c_int;
+    pub fn init_extended_pair(pair: c_int, f: c_int, b: c_int) -> c_int;
+}
+
+
+// From the C library's locale.h, which the libc crate doesn't bind. ncurses
+// only writes multibyte characters once the locale is set from the
+// environment.
+extern "C" {
+    pub fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+}
+#[cfg(target_os = "linux")]
+pub const LC_ALL: c_int = 6;
+#[cfg(not(target_os = "linux"))]
+pub const LC_ALL: c_int = 0;
@@ -1,8 +1,11 @@
 #![allow(missing_copy_implementations)]
 extern crate libc;
 
+use std::env;
 use std::ffi::CString;
 use self::libc::{c_int, c_char};
+#[cfg(feature = "ncursesw")]
+use self::libc::wchar_t;
 use ncurses::ext;
 
 
@@ -18,8 +21,82 @@ pub fn endwin() -> Result<(), c_int> {
 }
 
 
+/// Whether the terminal can show Unicode characters like block elements and
+/// Braille: this is built with ncursesw and the locale is UTF-8.
+pub fn supports_unicode() -> bool {
+    cfg!(feature = "ncursesw") && is_utf8_locale(|name| env::var(name).ok())
+}
+
+
+/// Whether the locale from the environment uses UTF-8. Like setlocale, this
+/// takes the first of LC_ALL, LC_CTYPE and LANG that is set.
+fn is_utf8_locale<F: Fn(&str) -> Option<String>>(var: F) -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        },
+        None => false,
+    }
+}
+
+
+/// Start using colors. Returns the number of colors the terminal has, which
+/// is 1 << 24 or more for terminals that take RGB values directly.
+pub fn start_color() -> Result<c_int, c_int> {
+    if !unsafe { ext::has_colors() } {
+        return Err(-1);
+    }
+    handle_err(unsafe { ext::start_color() })?;
+    Ok(unsafe { ext::COLORS })
+}
+
+
+/// Let color pairs use the terminal's default colors, as color -1. Without
+/// this, a pair's background has to be an actual color.
+pub fn use_default_colors() -> Result<(), c_int> {
+    handle_err(unsafe { ext::use_default_colors() }).map(|_| ())
+}
+
+
+/// Define color pair number pair as the foreground and background colors.
+/// Colors which don't fit in a short, like RGB values on direct color
+/// terminals, need ncursesw.
+pub fn init_pair(pair: i16, foreground: i32, background: i32) -> Result<(), c_int> {
+    let short = |color: i32| color >= i16::min_value() as i32 && color <= i16::max_value() as i32;
+    if short(foreground) && short(background) {
+        return handle_err(unsafe { ext::init_pair(pair, foreground as i16, background as i16) }).map(|_| ());
+    }
+    init_extended_pair(pair, foreground, background)
+}
+
+
+#[cfg(feature = "ncursesw")]
+fn init_extended_pair(pair: i16, foreground: i32, background: i32) -> Result<(), c_int> {
+    handle_err(unsafe { ext::init_extended_pair(pair as c_int, foreground, background) }).map(|_| ())
+}
+
+
+#[cfg(not(feature = "ncursesw"))]
+fn init_extended_pair(_: i16, _: i32, _: i32) -> Result<(), c_int> {
+    Err(-1)
+}
+
+
+/// The attribute for drawing with a color pair, like ncurses' COLOR_PAIR
+pub fn color_pair(pair: i16) -> c_int {
+    (pair as c_int) << 8
+}
+
+
 /// Initialize the screen and get a window
 fn initscr() -> Result<Window, c_int> {
+    // Use the environment's locale, so ncursesw can write UTF-8
+    let empty = CString::new("").unwrap();
+    unsafe { ext::setlocale(ext::LC_ALL, empty.as_ptr()) };
     let window = unsafe { ext::initscr() };
     if window.is_null() {
         Err(-1)
@@ -57,6 +134,36 @@ impl Window {
         })
     }
 
+    /// Add characters to the window. Without ncursesw, anything but ASCII is
+    /// written as '?'.
+    pub fn addchars(&mut self, y: c_int, x: c_int, text: &[char]) -> Result<c_int, c_int> {
+        self.addchars_impl(y, x, text)
+    }
+
+    #[cfg(feature = "ncursesw")]
+    fn addchars_impl(&mut self, y: c_int, x: c_int, text: &[char]) -> Result<c_int, c_int> {
+        let wide: Vec<wchar_t> = text.iter().map(|&c| c as wchar_t).collect();
+        handle_err(unsafe{
+            ext::mvwaddnwstr(self.w, y, x, wide.as_ptr(), wide.len() as c_int)
+        })
+    }
+
+    #[cfg(not(feature = "ncursesw"))]
+    fn addchars_impl(&mut self, y: c_int, x: c_int, text: &[char]) -> Result<c_int, c_int> {
+        let bytes: Vec<c_char> = text.iter().map(|&c| if c.is_ascii() { c as c_char } else { '?' as c_char }).collect();
+        self.addbytes(y, x, &bytes)
+    }
+
+    /// Turn on attributes, such as a color_pair, for whatever is added next
+    pub fn attron(&mut self, attrs: c_int) -> Result<c_int, c_int> {
+        handle_err(unsafe{ ext::wattron(self.w, attrs) })
+    }
+
+    /// Turn off attributes turned on by attron
+    pub fn attroff(&mut self, attrs: c_int) -> Result<c_int, c_int> {
+        handle_err(unsafe{ ext::wattroff(self.w, attrs) })
+    }
+
     /// Refresh the output on the display
     pub fn refresh(&mut self) -> Result<c_int, c_int> {
         handle_err(unsafe{ ext::wrefresh(self.w) })
@@ -98,3 +205,18 @@ fn handle_err(result: c_int) -> Result<c_int, c_int> {
         Ok(result)
     }
 }
+
+
+#[test]
+fn test_utf8_locale() {
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+        move |name: &str| vars.iter().find(|&&(var, _)| var == name).map(|&(_, value)| value.to_string())
+    };
+    assert!(is_utf8_locale(env(&[("LANG", "en_US.UTF-8")])));
+    assert!(is_utf8_locale(env(&[("LC_CTYPE", "de_DE.utf8"), ("LANG", "C")])));
+    // LC_ALL wins, and empty variables are skipped
+    assert!(!is_utf8_locale(env(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")])));
+    assert!(is_utf8_locale(env(&[("LC_ALL", ""), ("LANG", "en_US.UTF-8")])));
+    assert!(!is_utf8_locale(env(&[("LANG", "en_US.ISO-8859-1")])));
+    assert!(!is_utf8_locale(env(&[])));
+}
@@ -0,0 +1,144 @@
+/// Traits for the parts of PulseAudio the visualizer depends on.
+///
+/// Context and PulseAudioStream implement these by delegating to the real
+/// server. pulse::mock implements them in memory, so code written against the
+/// traits can be tested without a running daemon.
+
+extern crate libc;
+
+use self::libc::{c_int, size_t};
+
+use pulse::context::Context;
+use pulse::info::{ServerInfo, SinkInfo};
+use pulse::stream::{IoResult, PeekError, PulseAudioStream};
+use pulse::subscription_manager::SubscriptionEvent;
+use pulse::types::*;
+
+
+/// A connection to a PulseAudio server. Mirrors the Context API, except that
+/// callbacks get the backend back as Self and events arrive decoded.
+pub trait Backend<'a>: Clone + 'a {
+    /// The record stream type this backend creates
+    type Stream: BackendStream<'a>;
+
+    /// Set the callback for server state changes
+    fn set_state_callback<C>(&self, cb: C) where C: FnMut(Self, pa_context_state) + 'a;
+
+    /// Connect to the server
+    fn connect(&self, server: Option<&str>, flags: pa_context_flags);
+
+    /// Get basic information about the server
+    fn get_server_info<C>(&self, cb: C) where C: FnMut(Self, ServerInfo) + 'a;
+
+    /// Get information about a sink using its name. Calls back once with the
+    /// sink and once with None to mark the end of the list.
+    fn get_sink_info_by_name<C>(&self, name: &str, cb: C) where C: FnMut(Self, Option<SinkInfo>) + 'a;
+
+    /// Get information about every sink, followed by None
+    fn get_sink_info_list<C>(&self, cb: C) where C: FnMut(Self, Option<SinkInfo>) + 'a;
+
+    /// Add an event subscription
+    fn add_subscription<C>(&self, mask: pa_subscription_mask, cb: C) where C: FnMut(Self, bool) + 'a;
+
+    /// Set the callback for subscribed events
+    fn set_event_callback<C>(&self, cb: C) where C: FnMut(Self, SubscriptionEvent) + 'a;
+
+    /// Create an unconnected stream
+    fn create_stream(&mut self, name: &str, ss: &pa_sample_spec, map: Option<&pa_channel_map>) -> Self::Stream;
+}
+
+
+/// A record stream. Mirrors the PulseAudioStream API.
+pub trait BackendStream<'a>: Clone + 'a {
+    /// Return the current fragment. Call drop_fragment when done with it.
+    fn peek(&mut self) -> Result<&[u8], PeekError>;
+
+    /// Drop the current fragment
+    fn drop_fragment(&mut self) -> IoResult<c_int>;
+
+    /// Record from a source
+    fn connect_record(
+        &mut self,
+        source_name: Option<&str>,
+        buffer_attributes: Option<&pa_buffer_attr>,
+        stream_flags: Option<pa_stream_flags_t>) -> Result<c_int, String>;
+
+    /// Disconnect from the source
+    fn disconnect(&mut self) -> c_int;
+
+    /// Set the callback for when data is available to read
+    fn set_read_callback<C>(&mut self, cb: C) where C: FnMut(Self, size_t) + 'a;
+
+    /// Check if two handles refer to the same underlying stream
+    fn same_stream(&self, other: &Self) -> bool;
+}
+
+
+impl<'a> Backend<'a> for Context<'a> {
+    type Stream = PulseAudioStream<'a>;
+
+    fn set_state_callback<C>(&self, cb: C) where C: FnMut(Self, pa_context_state) + 'a {
+        Context::set_state_callback(self, cb)
+    }
+
+    fn connect(&self, server: Option<&str>, flags: pa_context_flags) {
+        Context::connect(self, server, flags)
+    }
+
+    fn get_server_info<C>(&self, cb: C) where C: FnMut(Self, ServerInfo) + 'a {
+        Context::get_server_info(self, cb)
+    }
+
+    fn get_sink_info_by_name<C>(&self, name: &str, cb: C) where C: FnMut(Self, Option<SinkInfo>) + 'a {
+        Context::get_sink_info_by_name(self, name, cb)
+    }
+
+    fn get_sink_info_list<C>(&self, cb: C) where C: FnMut(Self, Option<SinkInfo>) + 'a {
+        Context::get_sink_info_list(self, cb)
+    }
+
+    fn add_subscription<C>(&self, mask: pa_subscription_mask, cb: C) where C: FnMut(Self, bool) + 'a {
+        Context::add_subscription(self, mask, cb)
+    }
+
+    fn set_event_callback<C>(&self, mut cb: C) where C: FnMut(Self, SubscriptionEvent) + 'a {
+        Context::set_event_callback(self, move |context, event, index| {
+            cb(context, SubscriptionEvent::from_raw(event, index))
+        })
+    }
+
+    fn create_stream(&mut self, name: &str, ss: &pa_sample_spec, map: Option<&pa_channel_map>) -> PulseAudioStream<'a> {
+        Context::create_stream(self, name, ss, map)
+    }
+}
+
+
+impl<'a> BackendStream<'a> for PulseAudioStream<'a> {
+    fn peek(&mut self) -> Result<&[u8], PeekError> {
+        PulseAudioStream::peek(self)
+    }
+
+    fn drop_fragment(&mut self) -> IoResult<c_int> {
+        PulseAudioStream::drop_fragment(self)
+    }
+
+    fn connect_record(
+        &mut self,
+        source_name: Option<&str>,
+        buffer_attributes: Option<&pa_buffer_attr>,
+        stream_flags: Option<pa_stream_flags_t>) -> Result<c_int, String> {
+        PulseAudioStream::connect_record(self, source_name, buffer_attributes, stream_flags)
+    }
+
+    fn disconnect(&mut self) -> c_int {
+        PulseAudioStream::disconnect(self)
+    }
+
+    fn set_read_callback<C>(&mut self, cb: C) where C: FnMut(Self, size_t) + 'a {
+        PulseAudioStream::set_read_callback(self, cb)
+    }
+
+    fn same_stream(&self, other: &Self) -> bool {
+        self.get_raw_ptr() == other.get_raw_ptr()
+    }
+}
@@ -10,6 +10,7 @@ use std::ptr;
 use std::rc::Rc;
 
 use pulse::ext;
+use pulse::info::{ServerInfo, SinkInfo};
 use pulse::mainloop::PulseAudioMainloop;
 use pulse::stream::PulseAudioStream;
 use pulse::types::*;
@@ -17,11 +18,11 @@ use pulse::subscription_manager::SubscriptionManager;
 
 
 /// Types for callback closures
-type StateCallback<'a> = FnMut(Context, pa_context_state) + 'a;
-type ServerInfoCallback<'a> = FnMut(Context, &pa_server_info) + 'a;
-type SinkInfoCallback<'a> = FnMut(Context, Option<&pa_sink_info>) + 'a;
-type SubscriptionCallback<'a> = FnMut(Context, c_int, u32) + 'a;
-type PaContextSuccessCallback<'a> = FnMut(Context, bool) + 'a;
+type StateCallback<'a> = FnMut(Context<'a>, pa_context_state) + 'a;
+type ServerInfoCallback<'a> = FnMut(Context<'a>, ServerInfo) + 'a;
+type SinkInfoCallback<'a> = FnMut(Context<'a>, Option<SinkInfo>) + 'a;
+type SubscriptionCallback<'a> = FnMut(Context<'a>, c_int, u32) + 'a;
+type PaContextSuccessCallback<'a> = FnMut(Context<'a>, bool) + 'a;
 
 
 /// Boxed types for callback closures.
@@ -55,7 +56,7 @@ impl<'a> Context<'a> {
 
     /// Set the callback for server state. This callback gets called many times.
     /// Do not start sending commands until this returns pa_context_state::READY
-    pub fn set_state_callback<C>(&self, cb: C) where C: FnMut(Context, pa_context_state) + 'a {
+    pub fn set_state_callback<C>(&self, cb: C) where C: FnMut(Context<'a>, pa_context_state) + 'a {
         let mut internal = self.internal.borrow_mut();
         internal.state_cb = Some(Box::new(cb));
         pa_context_set_state_callback(internal.ptr, _state_callback, internal.as_void_ptr());
@@ -71,9 +72,9 @@ impl<'a> Context<'a> {
         pa_context_connect(internal.ptr, server, flags, None);
     }
 
-    /// Gets basic information about the server. See the pa_server_info struct
+    /// Gets basic information about the server. See the ServerInfo struct
     /// for more details.
-    pub fn get_server_info<C>(&self, cb: C) where C: FnMut(Context, &pa_server_info) + 'a {
+    pub fn get_server_info<C>(&self, cb: C) where C: FnMut(Context<'a>, ServerInfo) + 'a {
         let mut internal = self.internal.borrow_mut();
         internal.server_info_cb = Some(Box::new(cb));
         pa_context_get_server_info(internal.ptr, _server_info_callback, internal.as_void_ptr());
@@ -85,14 +86,23 @@ impl<'a> Context<'a> {
     /// element list. You should get two callbacks from this function: one with
     /// the information about the sink, and one with None indicating the end of
     /// the list.
-    pub fn get_sink_info_by_name<C>(&self, name: &str, cb: C) where C: FnMut(Context, Option<&pa_sink_info>) + 'a {
+    pub fn get_sink_info_by_name<C>(&self, name: &str, cb: C) where C: FnMut(Context<'a>, Option<SinkInfo>) + 'a {
         let mut internal = self.internal.borrow_mut();
         internal.sink_info_cb = Some(Box::new(cb));
         pa_context_get_sink_info_by_name(internal.ptr, name, _sink_info_callback, internal.as_void_ptr());
     }
 
+    /// Get information about every sink on the server. The callback gets
+    /// called once per sink, then once more with None to mark the end of the
+    /// list.
+    pub fn get_sink_info_list<C>(&self, cb: C) where C: FnMut(Context<'a>, Option<SinkInfo>) + 'a {
+        let mut internal = self.internal.borrow_mut();
+        internal.sink_info_cb = Some(Box::new(cb));
+        pa_context_get_sink_info_list(internal.ptr, _sink_info_callback, internal.as_void_ptr());
+    }
+
     /// Adds an event subscription
-    pub fn add_subscription<C>(&self, mask: pa_subscription_mask, cb: C) where C: FnMut(Context, bool) + 'a {
+    pub fn add_subscription<C>(&self, mask: pa_subscription_mask, cb: C) where C: FnMut(Context<'a>, bool) + 'a {
         let mut internal = self.internal.borrow_mut();
         internal.context_success_cb = Some(Box::new(cb));
         internal.subscriptions.add(mask);
@@ -101,7 +111,7 @@ impl<'a> Context<'a> {
     }
 
     /// Removes an event subscription
-    pub fn remove_subscription<C>(&self, mask: pa_subscription_mask, cb: C) where C: FnMut(Context, bool) + 'a {
+    pub fn remove_subscription<C>(&self, mask: pa_subscription_mask, cb: C) where C: FnMut(Context<'a>, bool) + 'a {
         let mut internal = self.internal.borrow_mut();
         internal.context_success_cb = Some(Box::new(cb));
         internal.subscriptions.remove(mask);
@@ -110,7 +120,7 @@ impl<'a> Context<'a> {
     }
 
     /// Set the callback for subscriptions
-    pub fn set_event_callback<C>(&self, cb: C) where C: FnMut(Context, c_int, u32) + 'a {
+    pub fn set_event_callback<C>(&self, cb: C) where C: FnMut(Context<'a>, c_int, u32) + 'a {
         let mut internal = self.internal.borrow_mut();
         internal.event_cb = Some(Box::new(cb));
         pa_context_set_subscribe_callback(internal.ptr, _subscription_event_callback, internal.as_void_ptr());
@@ -200,7 +210,7 @@ impl<'a> ContextInternal<'a> {
     }
 
     /// Called back for get_server_info. Wraps the user's closure
-    fn server_info_callback(&mut self, info: &pa_server_info) {
+    fn server_info_callback(&mut self, info: ServerInfo) {
         let external = self.external.clone().unwrap();
         match self.server_info_cb {
             Some(ref mut cb) => cb(external, info),
@@ -209,7 +219,7 @@ impl<'a> ContextInternal<'a> {
     }
 
     /// Called back for the sink_info_list and get_sink_info commands
-    fn sink_info_callback(&mut self, info: Option<&pa_sink_info>) {
+    fn sink_info_callback(&mut self, info: Option<SinkInfo>) {
         let external = self.external.clone().unwrap();
         match self.sink_info_cb {
             Some(ref mut cb) => cb(external, info),
@@ -258,10 +268,10 @@ fn pa_context_connect(context: *mut opaque::pa_context, server_name: Option<&str
 
     assert!(!context.is_null());
 
+    let server_name = server_name.map(|name| CString::new(name).unwrap());
     let server: *const c_char = match server_name {
         None => ptr::null(),
-        //TODO: use CString here
-        Some(name) => name.as_ptr() as *const i8
+        Some(ref name) => name.as_ptr()
     };
 
     let spawn_api_ptr: *const opaque::pa_spawn_api = match spawn_api {
@@ -342,10 +352,11 @@ extern fn _state_callback(_: *mut pa_context, context: *mut c_void) {
 
 
 /// Server info callback for C to call. Takes a ContextInternal and calls its
-/// server_info_callback method.
+/// server_info_callback method with an owned copy of the info.
 extern fn _server_info_callback(_: *mut pa_context, info: *const pa_server_info, context: *mut c_void) {
     let context_internal = unsafe{ &mut * (context as *mut ContextInternal) };
-    context_internal.server_info_callback(unsafe{ &*info });
+    assert!(!info.is_null());
+    context_internal.server_info_callback(ServerInfo::from_raw(unsafe{ &*info }));
 }
 
 
@@ -355,7 +366,7 @@ extern fn _sink_info_callback(_: *mut pa_context, info: *const pa_sink_info, eol
     if eol == 1 || info.is_null() {
         context_internal.sink_info_callback(None);
     } else {
-        context_internal.sink_info_callback(Some(unsafe{ &*info }));
+        context_internal.sink_info_callback(Some(SinkInfo::from_raw(unsafe{ &*info })));
     }
 }
 
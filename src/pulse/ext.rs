@@ -54,6 +54,16 @@ extern {
         result: *mut c_int
     ) -> c_int;
 
+    pub fn pa_mainloop_iterate(
+        m: *mut opaque::pa_mainloop,
+        block: c_int,
+        retval: *mut c_int
+    ) -> c_int;
+
+    pub fn pa_mainloop_wakeup(m: *mut opaque::pa_mainloop);
+
+    pub fn pa_mainloop_free(m: *mut opaque::pa_mainloop);
+
     pub fn pa_signal_init(
         api: *mut opaque::pa_mainloop_api
     ) -> c_int;
@@ -138,5 +148,33 @@ pub mod stream {
             dev: *const c_char,
             attr: *const pa_buffer_attr,
             flags: pa_stream_flags_t) -> c_int;
+
+        /// Connects a stream to a sink for playback.
+        pub fn pa_stream_connect_playback(
+            s: *mut pa_stream,
+            dev: *const c_char,
+            attr: *const pa_buffer_attr,
+            flags: pa_stream_flags_t,
+            volume: *const pa_cvolume,
+            sync_stream: *mut pa_stream) -> c_int;
+
+        pub fn pa_stream_set_write_callback(
+            p: *mut opaque::pa_stream,
+            cb: pa_stream_request_cb_t,
+            userdata: *mut c_void);
+
+        /// Writes data to a playback stream. If free_cb is None, PulseAudio
+        /// makes its own copy of the data.
+        pub fn pa_stream_write(
+            p: *mut pa_stream,
+            data: *const c_void,
+            nbytes: size_t,
+            free_cb: Option<pa_free_cb_t>,
+            offset: i64,
+            seek: pa_seek_mode_t) -> c_int;
+
+        /// Returns the number of bytes that can be written without
+        /// overflowing the server's buffer.
+        pub fn pa_stream_writable_size(p: *mut pa_stream) -> size_t;
     }
 }
@@ -0,0 +1,266 @@
+/// An optional futures-based interface on top of the callback API.
+///
+/// Every PulseAudio operation still completes from inside a mainloop callback.
+/// The futures here just park the result in a shared slot and wake whoever is
+/// waiting on it, so they work with any executor that runs on the same thread
+/// as the mainloop. PulseAudioMainloop::block_on is the simplest such
+/// executor: it iterates the mainloop until the future resolves.
+
+extern crate futures_core;
+extern crate libc;
+
+use self::futures_core::Stream;
+use self::libc::c_int;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{self, Poll, Wake, Waker};
+
+use pulse::context::Context;
+use pulse::info::{ServerInfo, SinkInfo};
+use pulse::mainloop::{PulseAudioMainloop, SharedMainloop};
+use pulse::stream::{PeekError, PulseAudioStream};
+use pulse::subscription_manager::SubscriptionEvent;
+use pulse::types::*;
+
+
+/// The slot shared between a callback and the future or stream waiting on it.
+struct Shared<T> {
+    /// Values produced by callbacks that haven't been polled out yet
+    values: VecDeque<T>,
+    /// Set once a one-shot operation has produced its value, or a stream has
+    /// ended. Later callbacks for the same operation are ignored.
+    done: bool,
+    /// The waker of the last task that polled and got Pending
+    waker: Option<Waker>,
+}
+
+
+type SharedSlot<T> = Rc<RefCell<Shared<T>>>;
+
+
+fn new_slot<T>() -> SharedSlot<T> {
+    Rc::new(RefCell::new(Shared {
+        values: VecDeque::new(),
+        done: false,
+        waker: None,
+    }))
+}
+
+
+/// Push a value into the slot and wake the waiting task
+fn push_value<T>(slot: &SharedSlot<T>, value: T) {
+    let waker = {
+        let mut shared = slot.borrow_mut();
+        shared.values.push_back(value);
+        shared.waker.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+
+/// Complete a one-shot operation. Only the first call has any effect.
+fn complete<T>(slot: &SharedSlot<T>, value: T) {
+    if slot.borrow().done {
+        return;
+    }
+    slot.borrow_mut().done = true;
+    push_value(slot, value);
+}
+
+
+/// End a stream. Values pushed before it ended are still polled out first.
+fn end_stream<T>(slot: &SharedSlot<T>) {
+    let waker = {
+        let mut shared = slot.borrow_mut();
+        shared.done = true;
+        shared.waker.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+
+/// Take the next value out of the slot, or register the waker if there isn't
+/// one yet.
+fn poll_slot<T>(slot: &SharedSlot<T>, cx: &mut task::Context) -> Poll<T> {
+    let mut shared = slot.borrow_mut();
+    match shared.values.pop_front() {
+        Some(value) => Poll::Ready(value),
+        None => {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+
+/// Take the next value of a stream out of the slot, None once it's ended, or
+/// register the waker if there isn't one yet.
+fn poll_stream<T>(slot: &SharedSlot<T>, cx: &mut task::Context) -> Poll<Option<T>> {
+    let mut shared = slot.borrow_mut();
+    match shared.values.pop_front() {
+        Some(value) => Poll::Ready(Some(value)),
+        None if shared.done => Poll::Ready(None),
+        None => {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+
+/// A future which resolves when a PulseAudio operation calls back.
+pub struct OperationFuture<T> {
+    slot: SharedSlot<T>,
+}
+
+
+impl<T> Future for OperationFuture<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<T> {
+        poll_slot(&self.slot, cx)
+    }
+}
+
+
+/// A stream of subscription events from a context. Only ends if the server
+/// refuses the subscription; otherwise drop it and clear the subscription to
+/// stop receiving events.
+pub struct SubscriptionEvents {
+    slot: SharedSlot<SubscriptionEvent>,
+}
+
+
+impl Stream for SubscriptionEvents {
+    type Item = SubscriptionEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Option<SubscriptionEvent>> {
+        poll_stream(&self.slot, cx)
+    }
+}
+
+
+/// A stream of raw sample chunks read from a record stream. Each item is a
+/// copy of one fragment, in the stream's sample format.
+pub struct SampleStream {
+    slot: SharedSlot<Vec<u8>>,
+}
+
+
+impl Stream for SampleStream {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Option<Vec<u8>>> {
+        poll_slot(&self.slot, cx).map(Some)
+    }
+}
+
+
+impl<'a> Context<'a> {
+    /// Async version of get_server_info
+    pub fn server_info(&self) -> OperationFuture<ServerInfo> {
+        let slot = new_slot();
+        let cb_slot = slot.clone();
+        self.get_server_info(move |_, info| {
+            complete(&cb_slot, info);
+        });
+        OperationFuture { slot: slot }
+    }
+
+    /// Async version of get_sink_info_by_name. Resolves to None if there is
+    /// no sink with that name.
+    pub fn sink_info_by_name(&self, name: &str) -> OperationFuture<Option<SinkInfo>> {
+        let slot = new_slot();
+        let cb_slot = slot.clone();
+        self.get_sink_info_by_name(name, move |_, info| {
+            // The end of list marker comes after the sink itself, so whichever
+            // arrives first is the answer.
+            complete(&cb_slot, info);
+        });
+        OperationFuture { slot: slot }
+    }
+
+    /// Subscribe to events matching mask and get them back as a stream.
+    /// Replaces any callback set with set_event_callback.
+    pub fn subscription_events(&self, mask: pa_subscription_mask) -> SubscriptionEvents {
+        let slot = new_slot();
+        let cb_slot = slot.clone();
+        self.set_event_callback(move |_, event, index| {
+            push_value(&cb_slot, SubscriptionEvent::from_raw(event, index));
+        });
+        let end_slot = slot.clone();
+        self.add_subscription(mask, move |_, success| {
+            if !success {
+                end_stream(&end_slot);
+            }
+        });
+        SubscriptionEvents { slot: slot }
+    }
+}
+
+
+impl<'a> PulseAudioStream<'a> {
+    /// Get the data read from this stream as a stream of owned chunks.
+    /// Replaces any callback set with set_read_callback.
+    pub fn samples(&mut self) -> SampleStream {
+        let slot = new_slot();
+        let cb_slot = slot.clone();
+        self.set_read_callback(move |mut stream, _| {
+            loop {
+                match stream.peek() {
+                    Ok(data) => push_value(&cb_slot, data.to_vec()),
+                    Err(PeekError::BufferEmpty) => return,
+                    Err(PeekError::HoleInInputBuffer(_)) => {}
+                }
+                stream.drop_fragment().unwrap();
+            }
+        });
+        SampleStream { slot: slot }
+    }
+}
+
+
+/// A waker which interrupts the mainloop's poll, so a task woken from another
+/// thread gets polled again promptly. Does nothing once the mainloop is gone.
+struct MainloopWaker {
+    mainloop: Arc<SharedMainloop>,
+}
+
+
+impl Wake for MainloopWaker {
+    fn wake(self: Arc<Self>) {
+        self.mainloop.wakeup();
+    }
+}
+
+
+impl PulseAudioMainloop {
+    /// Get a waker which interrupts this mainloop when woken
+    fn waker(&self) -> Waker {
+        Waker::from(Arc::new(MainloopWaker { mainloop: self.get_shared() }))
+    }
+
+    /// Run the mainloop until the future completes and return its output.
+    /// Fails with pa_mainloop_iterate's error if the mainloop quits or fails
+    /// first.
+    pub fn block_on<F: Future>(&self, future: F) -> Result<F::Output, c_int> {
+        let waker = self.waker();
+        let mut cx = task::Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return Ok(output);
+            }
+            self.iterate(true)?;
+        }
+    }
+}
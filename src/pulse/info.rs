@@ -0,0 +1,213 @@
+/// Owned, safe copies of the info structs PulseAudio hands to its callbacks.
+///
+/// The pa_*_info structs only live for the duration of the callback that
+/// receives them and are full of raw pointers, any of which may be null.
+/// Context converts them into these types before calling back into Rust, so
+/// nothing outside of the pulse module needs to touch the repr(C) structs.
+
+extern crate libc;
+
+use self::libc::{c_int, c_char};
+use std::ffi::CStr;
+
+use pulse::types::*;
+
+
+/// Copy a C string into an owned String. Returns None for null pointers and
+/// replaces invalid UTF-8 with U+FFFD rather than failing.
+fn get_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe{ CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+    }
+}
+
+
+/// Turn PA_INVALID_INDEX into None
+fn get_index(index: u32) -> Option<u32> {
+    if index == PA_INVALID_INDEX {
+        None
+    } else {
+        Some(index)
+    }
+}
+
+
+/// An owned copy of pa_server_info
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerInfo {
+    pub user_name: Option<String>,
+    pub host_name: Option<String>,
+    pub server_version: Option<String>,
+    pub server_name: Option<String>,
+    /// The name of the default sink. None if the server has no sinks.
+    pub default_sink_name: Option<String>,
+    /// The name of the default source. None if the server has no sources.
+    pub default_source_name: Option<String>,
+    /// The default sample rate
+    pub sample_rate: u32,
+    /// The default number of channels
+    pub channels: u8,
+    pub cookie: u32,
+}
+
+
+impl ServerInfo {
+    /// Copy the fields out of a pa_server_info
+    pub fn from_raw(info: &pa_server_info) -> ServerInfo {
+        ServerInfo {
+            user_name: get_string(info.user_name),
+            host_name: get_string(info.host_name),
+            server_version: get_string(info.server_version),
+            server_name: get_string(info.server_name),
+            default_sink_name: get_string(info.default_sink_name),
+            default_source_name: get_string(info.default_source_name),
+            sample_rate: info.sample_spec.rate,
+            channels: info.sample_spec.channels,
+            cookie: info.cookie,
+        }
+    }
+}
+
+
+/// The state of a sink. Mirrors pa_sink_state_t.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SinkState {
+    /// Playing audio
+    Running,
+    /// Not playing anything, but not suspended
+    Idle,
+    /// The device is closed
+    Suspended,
+    /// Still being set up. Only seen by modules, not clients.
+    Init,
+    /// Being torn down. Only seen by modules, not clients.
+    Unlinked,
+    /// PA_SINK_INVALID_STATE, or a state this code doesn't know about
+    Invalid(c_int),
+}
+
+
+impl SinkState {
+    /// Decode a raw pa_sink_state_t
+    pub fn from_raw(state: c_int) -> SinkState {
+        match state {
+            0 => SinkState::Running,
+            1 => SinkState::Idle,
+            2 => SinkState::Suspended,
+            -2 => SinkState::Init,
+            -3 => SinkState::Unlinked,
+            other => SinkState::Invalid(other),
+        }
+    }
+}
+
+
+/// The set of pa_sink_flags_t flags for a sink
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SinkFlags {
+    bits: c_int,
+}
+
+
+impl SinkFlags {
+    /// Wrap a raw pa_sink_flags_t mask
+    pub fn from_raw(bits: c_int) -> SinkFlags {
+        SinkFlags { bits: bits }
+    }
+
+    /// Get the raw mask
+    pub fn bits(&self) -> c_int {
+        self.bits
+    }
+
+    /// Check if a flag is set
+    pub fn contains(&self, flag: pa_sink_flags_t) -> bool {
+        let flag = flag as c_int;
+        (self.bits & flag) == flag
+    }
+}
+
+
+/// An owned copy of pa_sink_info
+#[derive(Clone, Debug, PartialEq)]
+pub struct SinkInfo {
+    pub name: String,
+    pub index: u32,
+    pub description: Option<String>,
+    /// The sink's sample rate
+    pub sample_rate: u32,
+    /// The sink's number of channels
+    pub channels: u8,
+    /// Index of the module that owns this sink, if any
+    pub owner_module: Option<u32>,
+    pub mute: bool,
+    /// Index of the monitor source for this sink, if it has one
+    pub monitor_source: Option<u32>,
+    /// The name of the monitor source for this sink, if it has one
+    pub monitor_source_name: Option<String>,
+    /// Length of queued audio in the output buffer in microseconds
+    pub latency: pa_usec_t,
+    pub driver: Option<String>,
+    pub flags: SinkFlags,
+    /// The latency the device has been configured to in microseconds
+    pub configured_latency: pa_usec_t,
+    pub state: SinkState,
+    /// Index of the card this sink belongs to, if any
+    pub card: Option<u32>,
+}
+
+
+impl SinkInfo {
+    /// Copy the fields out of a pa_sink_info
+    pub fn from_raw(info: &pa_sink_info) -> SinkInfo {
+        SinkInfo {
+            name: get_string(info.name).unwrap_or_default(),
+            index: info.index,
+            description: get_string(info.description),
+            sample_rate: info.sample_spec.rate,
+            channels: info.sample_spec.channels,
+            owner_module: get_index(info.owner_module),
+            mute: info.mute != 0,
+            monitor_source: get_index(info.monitor_source),
+            monitor_source_name: get_string(info.monitor_source_name),
+            latency: info.latency,
+            driver: get_string(info.driver),
+            flags: SinkFlags::from_raw(info.flags),
+            configured_latency: info.configured_latency,
+            state: SinkState::from_raw(info.state),
+            card: get_index(info.card),
+        }
+    }
+}
+
+
+#[test]
+fn test_get_string() {
+    use std::ptr;
+
+    assert_eq!(get_string(ptr::null()), None);
+
+    let valid = b"alsa_output.analog-stereo\0";
+    assert_eq!(get_string(valid.as_ptr() as *const c_char),
+               Some("alsa_output.analog-stereo".to_string()));
+
+    // Invalid UTF-8 gets replaced rather than panicking
+    let invalid = b"caf\xe9\0";
+    assert_eq!(get_string(invalid.as_ptr() as *const c_char),
+               Some("caf\u{fffd}".to_string()));
+}
+
+
+#[test]
+fn test_sink_flags_and_state() {
+    let flags = SinkFlags::from_raw(0x0001 | 0x0020);
+    assert!(flags.contains(pa_sink_flags_t::PA_SINK_HW_VOLUME_CTRL));
+    assert!(flags.contains(pa_sink_flags_t::PA_SINK_DECIBEL_VOLUME));
+    assert!(!flags.contains(pa_sink_flags_t::PA_SINK_HARDWARE));
+
+    assert_eq!(SinkState::from_raw(2), SinkState::Suspended);
+    assert_eq!(SinkState::from_raw(42), SinkState::Invalid(42));
+    assert_eq!(get_index(PA_INVALID_INDEX), None);
+}
@@ -4,21 +4,51 @@ extern crate libc;
 
 use self::libc::c_int;
 
+use std::sync::{Arc, Mutex};
+
 use pulse::ext;
 use pulse::types::*;
 use pulse::context::Context;
 
 /// A struct which wraps the PulseAudio async main loop.
 pub struct PulseAudioMainloop {
-    internal: *mut pa_mainloop
+    internal: *mut pa_mainloop,
+    shared: Arc<SharedMainloop>,
+}
+
+
+/// The mainloop pointer, shared with wakers which may be on other threads and
+/// may outlive the mainloop. It's cleared before the mainloop is freed, and
+/// the lock keeps it from being freed in the middle of a wakeup.
+pub(crate) struct SharedMainloop {
+    mainloop: Mutex<Option<*mut pa_mainloop>>,
+}
+
+
+/// The pointer is only used for pa_mainloop_wakeup, which is documented as
+/// safe to call from any thread, and only while the lock is held
+unsafe impl Send for SharedMainloop {}
+unsafe impl Sync for SharedMainloop {}
+
+
+impl SharedMainloop {
+    /// Interrupt the mainloop's poll, if the mainloop still exists
+    #[cfg(feature = "async")]
+    pub(crate) fn wakeup(&self) {
+        if let Some(mainloop) = *self.mainloop.lock().unwrap() {
+            pa_mainloop_wakeup(mainloop);
+        }
+    }
 }
 
 
 impl<'a> PulseAudioMainloop {
     /// Create a new mainloop.
     pub fn new() -> PulseAudioMainloop {
+        let mainloop = pa_mainloop_new();
         PulseAudioMainloop{
-            internal: pa_mainloop_new()
+            internal: mainloop,
+            shared: Arc::new(SharedMainloop { mainloop: Mutex::new(Some(mainloop)) }),
         }
     }
 
@@ -38,6 +68,35 @@ impl<'a> PulseAudioMainloop {
         pa_mainloop_run(self.internal, &mut result);
         result
     }
+
+    /// Run a single iteration of the mainloop. If block is true, waits for
+    /// events when none are pending. Returns the number of dispatched sources,
+    /// or an error code if the mainloop was quit or failed.
+    pub fn iterate(&self, block: bool) -> Result<c_int, c_int> {
+        let mut result: c_int = 0;
+        let res = pa_mainloop_iterate(self.internal, block, &mut result);
+        if res < 0 {
+            Err(res)
+        } else {
+            Ok(res)
+        }
+    }
+
+    /// The mainloop pointer for wakers, which stops working once the
+    /// mainloop is dropped
+    #[cfg(feature = "async")]
+    pub(crate) fn get_shared(&self) -> Arc<SharedMainloop> {
+        self.shared.clone()
+    }
+}
+
+
+impl Drop for PulseAudioMainloop {
+    /// Frees the mainloop, once wakers can no longer reach it
+    fn drop(&mut self) {
+        *self.shared.mainloop.lock().unwrap() = None;
+        pa_mainloop_free(self.internal);
+    }
 }
 
 
@@ -47,6 +106,31 @@ pub fn pa_mainloop_run(mainloop: *mut opaque::pa_mainloop, result: &mut c_int) {
     let res = unsafe{ ext::pa_mainloop_run(mainloop, result as *mut c_int) };
     assert!(res == 0);
 }
+
+
+/// A rust wrapper around pa_mainloop_iterate
+pub(crate) fn pa_mainloop_iterate(mainloop: *mut opaque::pa_mainloop, block: bool, result: &mut c_int) -> c_int {
+    assert!(!mainloop.is_null());
+    unsafe{ ext::pa_mainloop_iterate(mainloop, block as c_int, result as *mut c_int) }
+}
+
+
+/// A rust wrapper around pa_mainloop_wakeup. Interrupts a blocking poll. This
+/// is the only mainloop function which is safe to call from another thread.
+#[cfg(feature = "async")]
+pub(crate) fn pa_mainloop_wakeup(mainloop: *mut opaque::pa_mainloop) {
+    assert!(!mainloop.is_null());
+    unsafe{ ext::pa_mainloop_wakeup(mainloop) };
+}
+
+
+/// A rust wrapper around pa_mainloop_free
+fn pa_mainloop_free(mainloop: *mut opaque::pa_mainloop) {
+    assert!(!mainloop.is_null());
+    unsafe{ ext::pa_mainloop_free(mainloop) };
+}
+
+
 /// A safe interface to pa_mainloop_get_api
 pub fn pa_mainloop_get_api(mainloop: *mut opaque::pa_mainloop) -> *mut opaque::pa_mainloop_api {
     assert!(!mainloop.is_null());
@@ -0,0 +1,447 @@
+/// An in-memory PulseAudio backend for tests.
+///
+/// MockBackend implements the Backend trait without talking to a server. Like
+/// the real API, every operation calls back asynchronously: callbacks are
+/// queued and only run when the test calls run_pending, which plays the part
+/// of the mainloop. Tests drive the simulated server with add_sink,
+/// set_default_sink and deliver_samples.
+
+extern crate libc;
+
+use self::libc::{c_int, size_t};
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use pulse::backend::{Backend, BackendStream};
+use pulse::info::{ServerInfo, SinkFlags, SinkInfo, SinkState};
+use pulse::stream::{IoResult, PeekError};
+use pulse::subscription_manager::{EventFacility, EventKind, SubscriptionEvent, SubscriptionManager};
+use pulse::types::*;
+
+
+/// Shared, re-entrant callback types. Callbacks are cloned out of the mock
+/// before being called so they are free to call back into it.
+type SharedStateCallback<'a> = Rc<RefCell<Box<dyn FnMut(MockBackend<'a>, pa_context_state) + 'a>>>;
+type SharedEventCallback<'a> = Rc<RefCell<Box<dyn FnMut(MockBackend<'a>, SubscriptionEvent) + 'a>>>;
+type SharedReadCallback<'a> = Rc<RefCell<Box<dyn FnMut(MockStream<'a>, size_t) + 'a>>>;
+
+/// A queued callback invocation
+type Task<'a> = Box<dyn FnOnce() + 'a>;
+
+
+/// A simulated PulseAudio server and context
+#[derive(Clone)]
+pub struct MockBackend<'a> {
+    internal: Rc<RefCell<MockBackendInternal<'a>>>
+}
+
+
+struct MockBackendInternal<'a> {
+    /// What get_server_info returns
+    server_info: ServerInfo,
+    /// Every sink on the simulated server
+    sinks: Vec<SinkInfo>,
+    /// Callback for state changes
+    state_cb: Option<SharedStateCallback<'a>>,
+    /// Callback for subscribed events
+    event_cb: Option<SharedEventCallback<'a>>,
+    /// The events the client has subscribed to
+    subscriptions: SubscriptionManager,
+    /// Callbacks waiting for run_pending
+    pending: VecDeque<Task<'a>>,
+    /// Every stream created on this backend, in order of creation
+    streams: Vec<MockStream<'a>>,
+    /// When set, stream disconnects fail and leave the stream connected
+    fail_disconnects: Rc<Cell<bool>>,
+}
+
+
+impl<'a> Default for MockBackend<'a> {
+    fn default() -> MockBackend<'a> {
+        MockBackend::new()
+    }
+}
+
+
+impl<'a> MockBackend<'a> {
+    /// Create a simulated server with no sinks
+    pub fn new() -> MockBackend<'a> {
+        let server_info = ServerInfo {
+            user_name: Some("mock".to_string()),
+            host_name: Some("localhost".to_string()),
+            server_version: Some("0.0.0".to_string()),
+            server_name: Some("mock".to_string()),
+            default_sink_name: None,
+            default_source_name: None,
+            sample_rate: 44100,
+            channels: 2,
+            cookie: 0,
+        };
+
+        MockBackend {
+            internal: Rc::new(RefCell::new(MockBackendInternal {
+                server_info: server_info,
+                sinks: Vec::new(),
+                state_cb: None,
+                event_cb: None,
+                subscriptions: SubscriptionManager::new(),
+                pending: VecDeque::new(),
+                streams: Vec::new(),
+                fail_disconnects: Rc::new(Cell::new(false)),
+            }))
+        }
+    }
+
+    /// Add a sink named name with a monitor source named "<name>.monitor".
+    /// The first sink added becomes the default sink.
+    pub fn add_sink(&self, name: &str) -> SinkInfo {
+        let sink = {
+            let mut internal = self.internal.borrow_mut();
+            let index = internal.sinks.len() as u32;
+            let sink = SinkInfo {
+                name: name.to_string(),
+                index: index,
+                description: Some(format!("Mock sink {}", name)),
+                sample_rate: 44100,
+                channels: 2,
+                owner_module: None,
+                mute: false,
+                monitor_source: Some(index),
+                monitor_source_name: Some(format!("{}.monitor", name)),
+                latency: 0,
+                driver: Some("mock".to_string()),
+                flags: SinkFlags::from_raw(0),
+                configured_latency: 0,
+                state: SinkState::Idle,
+                card: None,
+            };
+            internal.sinks.push(sink.clone());
+            if internal.server_info.default_sink_name.is_none() {
+                internal.server_info.default_sink_name = Some(name.to_string());
+            }
+            sink
+        };
+        self.emit_event(pa_subscription_mask::SINK, EventFacility::Sink, EventKind::New, sink.index);
+        sink
+    }
+
+    /// Change the default sink and notify subscribers of the server change
+    pub fn set_default_sink(&self, name: &str) {
+        self.internal.borrow_mut().server_info.default_sink_name = Some(name.to_string());
+        self.emit_event(pa_subscription_mask::SERVER, EventFacility::Server, EventKind::Change, 0);
+    }
+
+    /// Replace what get_server_info returns. Doesn't emit any events.
+    pub fn set_server_info(&self, info: ServerInfo) {
+        self.internal.borrow_mut().server_info = info;
+    }
+
+    /// Make stream disconnects fail, which leaves streams delivering data
+    /// after the client tried to get rid of them.
+    pub fn set_fail_disconnects(&self, fail: bool) {
+        self.internal.borrow().fail_disconnects.set(fail);
+    }
+
+    /// Queue a fragment of audio data on every connected stream recording
+    /// from source_name. Returns the number of streams it was delivered to.
+    pub fn deliver_samples(&self, source_name: &str, data: &[u8]) -> usize {
+        let streams = self.streams();
+        let mut delivered = 0;
+        for stream in streams.into_iter() {
+            if !stream.is_connected() || stream.source_name().as_ref().map(|s| &s[..]) != Some(source_name) {
+                continue;
+            }
+            stream.internal.borrow_mut().fragments.push_back(data.to_vec());
+            let nbytes = data.len() as size_t;
+            self.queue(move || {
+                let cb = stream.internal.borrow().read_cb.clone();
+                if let Some(cb) = cb {
+                    let handle = stream.clone();
+                    (*cb.borrow_mut())(handle, nbytes);
+                }
+            });
+            delivered += 1;
+        }
+        delivered
+    }
+
+    /// Run queued callbacks, including any they queue, until there are none
+    /// left. Returns the number of callbacks run.
+    pub fn run_pending(&self) -> usize {
+        let mut count = 0;
+        loop {
+            let task = self.internal.borrow_mut().pending.pop_front();
+            match task {
+                Some(task) => {
+                    task();
+                    count += 1;
+                },
+                None => return count
+            }
+        }
+    }
+
+    /// Get every stream created on this backend, in order of creation
+    pub fn streams(&self) -> Vec<MockStream<'a>> {
+        self.internal.borrow().streams.clone()
+    }
+
+    /// Queue a callback to be run by run_pending
+    fn queue<F>(&self, task: F) where F: FnOnce() + 'a {
+        self.internal.borrow_mut().pending.push_back(Box::new(task));
+    }
+
+    /// Queue a state change
+    fn queue_state(&self, state: pa_context_state) {
+        let backend = self.clone();
+        self.queue(move || {
+            let cb = backend.internal.borrow().state_cb.clone();
+            if let Some(cb) = cb {
+                (*cb.borrow_mut())(backend.clone(), state);
+            }
+        });
+    }
+
+    /// Queue an event if the client subscribed to it
+    fn emit_event(&self, mask: pa_subscription_mask, facility: EventFacility, kind: EventKind, index: u32) {
+        if !self.internal.borrow().subscriptions.is_enabled(mask) {
+            return;
+        }
+        let event = SubscriptionEvent {
+            facility: facility,
+            kind: kind,
+            index: index,
+        };
+        let backend = self.clone();
+        self.queue(move || {
+            let cb = backend.internal.borrow().event_cb.clone();
+            if let Some(cb) = cb {
+                (*cb.borrow_mut())(backend.clone(), event);
+            }
+        });
+    }
+}
+
+
+impl<'a> Backend<'a> for MockBackend<'a> {
+    type Stream = MockStream<'a>;
+
+    fn set_state_callback<C>(&self, cb: C) where C: FnMut(Self, pa_context_state) + 'a {
+        self.internal.borrow_mut().state_cb = Some(Rc::new(RefCell::new(Box::new(cb))));
+    }
+
+    fn connect(&self, _: Option<&str>, _: pa_context_flags) {
+        self.queue_state(pa_context_state::CONNECTING);
+        self.queue_state(pa_context_state::READY);
+    }
+
+    fn get_server_info<C>(&self, mut cb: C) where C: FnMut(Self, ServerInfo) + 'a {
+        let backend = self.clone();
+        self.queue(move || {
+            let info = backend.internal.borrow().server_info.clone();
+            cb(backend, info);
+        });
+    }
+
+    fn get_sink_info_by_name<C>(&self, name: &str, mut cb: C) where C: FnMut(Self, Option<SinkInfo>) + 'a {
+        let backend = self.clone();
+        let name = name.to_string();
+        self.queue(move || {
+            let sink = backend.internal.borrow().sinks.iter()
+                .find(|sink| sink.name == name)
+                .cloned();
+            if sink.is_some() {
+                cb(backend.clone(), sink);
+            }
+            cb(backend, None);
+        });
+    }
+
+    fn get_sink_info_list<C>(&self, mut cb: C) where C: FnMut(Self, Option<SinkInfo>) + 'a {
+        let backend = self.clone();
+        self.queue(move || {
+            let sinks = backend.internal.borrow().sinks.clone();
+            for sink in sinks.into_iter() {
+                cb(backend.clone(), Some(sink));
+            }
+            cb(backend, None);
+        });
+    }
+
+    fn add_subscription<C>(&self, mask: pa_subscription_mask, mut cb: C) where C: FnMut(Self, bool) + 'a {
+        self.internal.borrow_mut().subscriptions.add(mask);
+        let backend = self.clone();
+        self.queue(move || cb(backend, true));
+    }
+
+    fn set_event_callback<C>(&self, cb: C) where C: FnMut(Self, SubscriptionEvent) + 'a {
+        self.internal.borrow_mut().event_cb = Some(Rc::new(RefCell::new(Box::new(cb))));
+    }
+
+    fn create_stream(&mut self, name: &str, _: &pa_sample_spec, _: Option<&pa_channel_map>) -> MockStream<'a> {
+        let mut internal = self.internal.borrow_mut();
+        let stream = MockStream {
+            internal: Rc::new(RefCell::new(MockStreamInternal {
+                name: name.to_string(),
+                source_name: None,
+                connected: false,
+                fragments: VecDeque::new(),
+                read_cb: None,
+                disconnect_count: 0,
+                fail_disconnects: internal.fail_disconnects.clone(),
+            })),
+            current: Vec::new(),
+        };
+        internal.streams.push(stream.clone());
+        stream
+    }
+}
+
+
+/// A simulated record stream
+#[derive(Clone)]
+pub struct MockStream<'a> {
+    internal: Rc<RefCell<MockStreamInternal<'a>>>,
+    /// A copy of the fragment returned by the last peek through this handle
+    current: Vec<u8>,
+}
+
+
+struct MockStreamInternal<'a> {
+    /// The name the stream was created with
+    name: String,
+    /// The source passed to connect_record
+    source_name: Option<String>,
+    /// Whether the stream is recording
+    connected: bool,
+    /// Delivered fragments that haven't been dropped yet
+    fragments: VecDeque<Vec<u8>>,
+    /// Called when a fragment is delivered
+    read_cb: Option<SharedReadCallback<'a>>,
+    /// The number of times disconnect was called
+    disconnect_count: usize,
+    /// Shared with the backend. See MockBackend::set_fail_disconnects.
+    fail_disconnects: Rc<Cell<bool>>,
+}
+
+
+impl<'a> MockStream<'a> {
+    /// The name the stream was created with
+    pub fn name(&self) -> String {
+        self.internal.borrow().name.clone()
+    }
+
+    /// The source the stream is recording from
+    pub fn source_name(&self) -> Option<String> {
+        self.internal.borrow().source_name.clone()
+    }
+
+    /// Whether the stream is connected to a source
+    pub fn is_connected(&self) -> bool {
+        self.internal.borrow().connected
+    }
+
+    /// The number of times disconnect was called on this stream
+    pub fn disconnect_count(&self) -> usize {
+        self.internal.borrow().disconnect_count
+    }
+
+    /// The number of delivered fragments that haven't been dropped
+    pub fn pending_fragments(&self) -> usize {
+        self.internal.borrow().fragments.len()
+    }
+}
+
+
+impl<'a> BackendStream<'a> for MockStream<'a> {
+    fn peek(&mut self) -> Result<&[u8], PeekError> {
+        let fragment = self.internal.borrow().fragments.front().cloned();
+        match fragment {
+            Some(data) => {
+                self.current = data;
+                Ok(&self.current[..])
+            },
+            None => Err(PeekError::BufferEmpty)
+        }
+    }
+
+    fn drop_fragment(&mut self) -> IoResult<c_int> {
+        match self.internal.borrow_mut().fragments.pop_front() {
+            Some(_) => Ok(0),
+            None => Err("no fragment to drop".to_string())
+        }
+    }
+
+    fn connect_record(
+        &mut self,
+        source_name: Option<&str>,
+        _: Option<&pa_buffer_attr>,
+        _: Option<pa_stream_flags_t>) -> Result<c_int, String> {
+        let mut internal = self.internal.borrow_mut();
+        internal.source_name = source_name.map(|name| name.to_string());
+        internal.connected = true;
+        Ok(0)
+    }
+
+    fn disconnect(&mut self) -> c_int {
+        let mut internal = self.internal.borrow_mut();
+        internal.disconnect_count += 1;
+        if internal.fail_disconnects.get() {
+            -1
+        } else {
+            internal.connected = false;
+            internal.fragments.clear();
+            0
+        }
+    }
+
+    fn set_read_callback<C>(&mut self, cb: C) where C: FnMut(Self, size_t) + 'a {
+        self.internal.borrow_mut().read_cb = Some(Rc::new(RefCell::new(Box::new(cb))));
+    }
+
+    fn same_stream(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.internal, &other.internal)
+    }
+}
+
+
+#[test]
+fn test_mock_sink_lookup() {
+    let backend = MockBackend::new();
+    backend.add_sink("speakers");
+    backend.add_sink("headphones");
+
+    let found = Rc::new(RefCell::new(Vec::new()));
+    let found_cb = found.clone();
+    backend.get_sink_info_by_name("headphones", move |_, info| {
+        found_cb.borrow_mut().push(info.map(|info| info.monitor_source_name.unwrap()));
+    });
+    assert!(found.borrow().is_empty());
+
+    backend.run_pending();
+    assert_eq!(*found.borrow(), vec![Some("headphones.monitor".to_string()), None]);
+}
+
+
+#[test]
+fn test_mock_events_need_subscription() {
+    let backend = MockBackend::new();
+    backend.add_sink("speakers");
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let events_cb = events.clone();
+    backend.set_event_callback(move |_, event| events_cb.borrow_mut().push(event));
+
+    backend.set_default_sink("speakers");
+    backend.run_pending();
+    assert!(events.borrow().is_empty());
+
+    backend.add_subscription(pa_subscription_mask::SERVER, |_, success| assert!(success));
+    backend.set_default_sink("speakers");
+    backend.run_pending();
+    assert_eq!(events.borrow().len(), 1);
+    assert_eq!(events.borrow()[0].facility, EventFacility::Server);
+    assert_eq!(events.borrow()[0].kind, EventKind::Change);
+}
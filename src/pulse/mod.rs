@@ -3,8 +3,14 @@ pub use self::mainloop::PulseAudioMainloop;
 pub use self::stream::PulseAudioStream;
 
 mod ext;
+pub mod backend;
 pub mod context;
+pub mod info;
 pub mod mainloop;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod stream;
 pub mod subscription_manager;
 pub mod types;
+#[cfg(feature = "async")]
+pub mod future;
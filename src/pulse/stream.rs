@@ -12,7 +12,7 @@ use std::cell::RefCell;
 use pulse::types::*;
 
 // Types for callback closures
-pub type PaStreamRequestCallback<'a> = FnMut(PulseAudioStream, size_t) + 'a; // XXX
+pub type PaStreamRequestCallback<'a> = FnMut(PulseAudioStream<'a>, size_t) + 'a; // XXX
 pub type BoxedPaStreamRequestCallback<'a> = Box<PaStreamRequestCallback<'a>>;
 
 pub type IoResult<T>=Result<T, String>;
@@ -40,6 +40,16 @@ mod safe {
     }
 
 
+    /// Wrapper for a PulseAudio stream write callback. Called by C when the
+    /// server wants more data for a playback stream.
+    pub extern "C" fn _pa_stream_write_callback(
+        _: *mut opaque::pa_stream, nbytes: size_t,  userdata: *mut c_void) {
+        let stream_internal = unsafe{ &mut * (
+            userdata as *mut PulseAudioStreamInternal) };
+        stream_internal.write_callback(nbytes);
+    }
+
+
     /// Set a callback for when there's data available to be read.
     pub fn pa_stream_set_read_callback(
         p: *mut opaque::pa_stream,
@@ -50,6 +60,16 @@ mod safe {
     }
 
 
+    /// Set a callback for when a playback stream wants more data.
+    pub fn pa_stream_set_write_callback(
+        p: *mut opaque::pa_stream,
+        cb: pa_stream_request_cb_t,
+        userdata: *mut c_void) {
+        assert!(!p.is_null());
+        unsafe { ext::stream::pa_stream_set_write_callback(p, cb, userdata) }
+    }
+
+
     /// Create a new pa_stream
     pub fn pa_stream_new(c: *mut opaque::pa_context, name: &str, ss: *const pa_sample_spec, map: *const pa_channel_map) -> *mut opaque::pa_stream {
         assert!(!c.is_null());
@@ -116,6 +136,63 @@ mod safe {
     }
 
 
+    /// Sets a pa_stream to play back to a sink.
+    pub fn pa_stream_connect_playback(
+        stream: *mut opaque::pa_stream,
+        sink_name: Option<&str>,
+        buffer_attributes: Option<&pa_buffer_attr>,
+        stream_flags: Option<pa_stream_flags_t>) -> Result<c_int, String> {
+
+        assert!(!stream.is_null());
+
+        let dev = sink_name.map(|name| CString::new(name).unwrap());
+        let dev_ptr: *const c_char = match dev {
+            None => ptr::null(),
+            Some(ref name) => name.as_ptr()
+        };
+
+        let attr: *const pa_buffer_attr = match buffer_attributes {
+            None => ptr::null(),
+            Some(attributes) => attributes
+        };
+
+        let flags: pa_stream_flags_t = match stream_flags {
+            None => pa_stream_flags_t::PA_STREAM_NOFLAGS,
+            Some(stream_flags) => stream_flags
+        };
+
+        let res = unsafe {
+            ext::stream::pa_stream_connect_playback(
+                stream, dev_ptr, attr, flags, ptr::null(), ptr::null_mut())
+        };
+
+        if res < 0 {
+            Err("unknown error".to_string())
+        } else {
+            Ok(res)
+        }
+    }
+
+
+    /// Writes data to a playback stream. PulseAudio copies the data, so the
+    /// slice doesn't need to outlive the call.
+    pub fn pa_stream_write(stream: *mut pa_stream, data: &[u8]) -> c_int {
+        assert!(!stream.is_null());
+        unsafe {
+            ext::stream::pa_stream_write(
+                stream, data.as_ptr() as *const c_void, data.len() as size_t,
+                None, 0, pa_seek_mode_t::PA_SEEK_RELATIVE)
+        }
+    }
+
+
+    /// The number of bytes that can be written to a playback stream.
+    pub fn pa_stream_writable_size(stream: *mut pa_stream) -> size_t {
+        assert!(!stream.is_null());
+        unsafe { ext::stream::pa_stream_writable_size(stream) }
+    }
+
+
     /// Drops the stream's current fragment, freeing up the input buffer.
     /// Should only be called after peek.
     pub fn pa_stream_drop(stream: *mut pa_stream) -> c_int {
@@ -140,7 +217,9 @@ struct PulseAudioStreamInternal<'a> {
     /// A pointer to the external PulseAudioStream
     external: Option<PulseAudioStream<'a>>,
     /// Called when the stream has data available for reading
-    read_cb: Option<BoxedPaStreamRequestCallback<'a>>
+    read_cb: Option<BoxedPaStreamRequestCallback<'a>>,
+    /// Called when a playback stream wants more data
+    write_cb: Option<BoxedPaStreamRequestCallback<'a>>
 }
 
 
@@ -150,7 +229,8 @@ impl<'a> PulseAudioStreamInternal<'a> {
         PulseAudioStreamInternal {
             pa_stream: stream,
             external: None,
-            read_cb: None
+            read_cb: None,
+            write_cb: None
         }
     }
 
@@ -167,6 +247,18 @@ impl<'a> PulseAudioStreamInternal<'a> {
         }
     }
 
+    /// Called when the underlying playback stream wants more data.
+    pub fn write_callback(&mut self, nbytes: size_t) {
+        assert!(!self.external.is_none());
+        assert!(!self.pa_stream.is_null());
+
+        let external = self.external.clone().unwrap();
+        match self.write_cb {
+            Some(ref mut cb) => cb(external, nbytes),
+            None => println!("[PulseAudioStream] warning: write callback called, no write callback set.")
+        }
+    }
+
     /// Get a c_void pointer to this object
     pub fn as_void_ptr(&mut self) -> *mut c_void {
         self.as_mut_ptr() as *mut c_void
@@ -288,6 +380,41 @@ impl<'a> PulseAudioStream<'a> {
             internal.pa_stream, source_name, buffer_attributes, stream_flags)
     }
 
+    /// Play back to a sink.
+    /// Args:
+    ///    sink_name: The name of the sink to play to. If none, use the
+    ///        default sink.
+    ///    buffer_attributes: Options on the default buffer.
+    ///    stream_flags: Options for the stream.
+    pub fn connect_playback(
+        &mut self,
+        sink_name: Option<&str>,
+        buffer_attributes: Option<&pa_buffer_attr>,
+        stream_flags: Option<pa_stream_flags_t>) -> Result<c_int, String> {
+        let internal = self.internal.borrow_mut();
+        safe::pa_stream_connect_playback(
+            internal.pa_stream, sink_name, buffer_attributes, stream_flags)
+    }
+
+    /// Write data to a playback stream. Usually called from the write
+    /// callback with at most the number of bytes it asked for.
+    pub fn write(&mut self, data: &[u8]) -> Result<c_int, c_int> {
+        let internal = self.internal.borrow_mut();
+        let res = safe::pa_stream_write(internal.pa_stream, data);
+        if res < 0 {
+            Err(res)
+        } else {
+            Ok(res)
+        }
+    }
+
+    /// The number of bytes that can be written to a playback stream without
+    /// overflowing the server's buffer.
+    pub fn writable_size(&self) -> usize {
+        let internal = self.internal.borrow();
+        safe::pa_stream_writable_size(internal.pa_stream) as usize
+    }
+
     /// Disconnect the stream from its source/sink.
     pub fn disconnect(&mut self) -> c_int {
         let internal = self.internal.borrow_mut();
@@ -295,7 +422,7 @@ impl<'a> PulseAudioStream<'a> {
     }
 
     /// Sets the read callback
-    pub fn set_read_callback<C>(&mut self, cb: C) where C: FnMut(PulseAudioStream, size_t) + 'a {
+    pub fn set_read_callback<C>(&mut self, cb: C) where C: FnMut(PulseAudioStream<'a>, size_t) + 'a {
         let mut internal = self.internal.borrow_mut();
         internal.read_cb = Some(Box::new(cb) as BoxedPaStreamRequestCallback);
         safe::pa_stream_set_read_callback(
@@ -303,4 +430,14 @@ impl<'a> PulseAudioStream<'a> {
             safe::_pa_stream_read_callback,
             internal.as_void_ptr());
     }
+
+    /// Sets the write callback, called when a playback stream wants data
+    pub fn set_write_callback<C>(&mut self, cb: C) where C: FnMut(PulseAudioStream<'a>, size_t) + 'a {
+        let mut internal = self.internal.borrow_mut();
+        internal.write_cb = Some(Box::new(cb) as BoxedPaStreamRequestCallback);
+        safe::pa_stream_set_write_callback(
+            internal.pa_stream,
+            safe::_pa_stream_write_callback,
+            internal.as_void_ptr());
+    }
 }
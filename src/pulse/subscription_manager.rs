@@ -3,7 +3,7 @@
 extern crate libc;
 
 use self::libc::c_int;
-use pulse::types::pa_subscription_mask;
+use pulse::types::{pa_subscription_mask, pa_subscription_event_type};
 
 
 /// Helper for managing the subscription mask for subscribed events.
@@ -43,3 +43,90 @@ impl SubscriptionManager {
         (self.mask & sub_int) == sub_int
     }
 }
+
+
+/// The kind of object a subscription event is about. Decoded from the
+/// FACILITY_MASK bits of the event type PulseAudio passes to the callback.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EventFacility {
+    Sink,
+    Source,
+    SinkInput,
+    SourceOutput,
+    Module,
+    Client,
+    SampleCache,
+    Server,
+    Autoload,
+    Card,
+    Unknown(c_int),
+}
+
+
+/// What happened to the object. Decoded from the TYPE_MASK bits of the event
+/// type PulseAudio passes to the callback.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    New,
+    Change,
+    Remove,
+    Unknown(c_int),
+}
+
+
+/// A decoded subscription event
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SubscriptionEvent {
+    /// The kind of object the event is about
+    pub facility: EventFacility,
+    /// What happened to it
+    pub kind: EventKind,
+    /// The index of the object
+    pub index: u32,
+}
+
+
+impl SubscriptionEvent {
+    /// Decode the raw event type and index passed to the subscribe callback
+    pub fn from_raw(event: c_int, index: u32) -> SubscriptionEvent {
+        let facility = match event & (pa_subscription_event_type::FACILITY_MASK as c_int) {
+            0x0000 => EventFacility::Sink,
+            0x0001 => EventFacility::Source,
+            0x0002 => EventFacility::SinkInput,
+            0x0003 => EventFacility::SourceOutput,
+            0x0004 => EventFacility::Module,
+            0x0005 => EventFacility::Client,
+            0x0006 => EventFacility::SampleCache,
+            0x0007 => EventFacility::Server,
+            0x0008 => EventFacility::Autoload,
+            0x0009 => EventFacility::Card,
+            other => EventFacility::Unknown(other),
+        };
+
+        let kind = match event & (pa_subscription_event_type::TYPE_MASK as c_int) {
+            0x0000 => EventKind::New,
+            0x0010 => EventKind::Change,
+            0x0020 => EventKind::Remove,
+            other => EventKind::Unknown(other),
+        };
+
+        SubscriptionEvent {
+            facility: facility,
+            kind: kind,
+            index: index,
+        }
+    }
+}
+
+
+#[test]
+fn test_decode_subscription_event() {
+    let event = SubscriptionEvent::from_raw(0x0017, 3);
+    assert_eq!(event.facility, EventFacility::Server);
+    assert_eq!(event.kind, EventKind::Change);
+    assert_eq!(event.index, 3);
+
+    let event = SubscriptionEvent::from_raw(0x0020, 0);
+    assert_eq!(event.facility, EventFacility::Sink);
+    assert_eq!(event.kind, EventKind::Remove);
+}
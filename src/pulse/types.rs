@@ -52,6 +52,8 @@ pub mod cb {
         success: c_int,
         userdata: *mut c_void,
     );
+
+    pub type pa_free_cb_t = extern "C" fn(p: *mut c_void);
 }
 
 /// For types we only have pointers to. Use structs with names so there is at
@@ -78,6 +80,15 @@ pub mod enums {
         StreamUpload
     }
 
+    #[repr(C)]
+    #[derive(Copy,Clone)]
+    pub enum pa_seek_mode_t {
+        PA_SEEK_RELATIVE = 0,         // Seek relative to the write index.
+        PA_SEEK_ABSOLUTE = 1,         // Seek relative to the start of the buffer queue.
+        PA_SEEK_RELATIVE_ON_READ = 2, // Seek relative to the read index.
+        PA_SEEK_RELATIVE_END = 3,     // Seek relative to the current end of the buffer queue.
+    }
+
     #[repr(C)]
     #[derive(Copy,Clone)]
     pub enum pa_context_flags {
@@ -100,7 +111,7 @@ pub mod enums {
     }
 
     #[repr(C)]
-    #[derive(Copy,Clone)]
+    #[derive(Copy,Clone,PartialEq,Eq,Debug)]
     pub enum pa_sample_format {
         PA_SAMPLE_U8,
         PA_SAMPLE_ALAW,
@@ -177,6 +188,7 @@ pub mod enums {
     }
 
     #[repr(C)]
+    #[derive(Copy,Clone,PartialEq,Eq,Debug)]
         pub enum pa_sink_state_t {
         PA_SINK_INVALID_STATE = -1,
         PA_SINK_RUNNING = 0,
@@ -187,6 +199,7 @@ pub mod enums {
     }
 
     #[repr(C)]
+    #[derive(Copy,Clone,PartialEq,Eq,Debug)]
         pub enum pa_sink_flags_t {
         PA_SINK_NOFLAGS = 0x0000isize,
         PA_SINK_HW_VOLUME_CTRL = 0x0001isize,
@@ -317,8 +330,7 @@ pub mod enums {
 
 pub mod structs {
     extern crate libc;
-    use self::libc::{c_int, c_char, c_void, strlen};
-    use std::{str, slice, mem};
+    use self::libc::{c_int, c_char, c_void};
     use super::types::*;
     use super::enums::*;
     use super::opaque::*;
@@ -356,11 +368,11 @@ pub mod structs {
         pub monitor_source_name: *const c_char,   //**< The name of the monitor source. */
         pub latency: pa_usec_t,                 //**< Length of queued audio in the output buffer. */
         pub driver: *const c_char,                //**< Driver name */
-        pub flags: pa_sink_flags_t,             //**< Flags */
+        pub flags: c_int,                       //**< Flags (pa_sink_flags_t). Kept raw since PulseAudio may OR them together or add new ones. */
         pub proplist: *mut pa_proplist,             //**< Property list \since 0.9.11 */
         pub configured_latency: pa_usec_t,      //**< The latency this device has been configured to. \since 0.9.11 */
         pub base_volume: pa_volume_t,           //**< Some kind of "base" volume that refers to unamplified/unattenuated volume in the context of the output device. \since 0.9.15 */
-        pub state: pa_sink_state_t,             //**< State \since 0.9.15 */
+        pub state: c_int,                       //**< State (pa_sink_state_t). Kept raw since newer servers may send states we don't know. \since 0.9.15 */
         pub n_volume_steps: u32,           //**< Number of volume steps for sinks which do not support arbitrary volumes. \since 0.9.15 */
         pub card: u32,                     //**< Card index, or PA_INVALID_INDEX. \since 0.9.15 */
         pub n_ports: u32,                  //**< Number of entries in port array \since 0.9.16 */
@@ -400,63 +412,13 @@ pub mod structs {
         pub minreq: u32,
         pub fragsize: u32
     }
-
-    /// Impl for making it easy to get string values from pa_server_info
-    impl<'a> pa_server_info {
-        pub fn get_user_name(&'a self) -> &'a str {
-            get_str(&self.user_name)
-        }
-
-        pub fn get_host_name(&'a self) -> &'a str {
-            get_str(&self.host_name)
-        }
-
-        pub fn get_server_version(&'a self) -> &'a str {
-            get_str(&self.server_version)
-        }
-
-        pub fn get_server_name(&'a self) -> &'a str {
-            get_str(&self.server_name)
-        }
-
-        pub fn get_default_sink_name(&'a self) -> &'a str {
-            get_str(&self.default_sink_name)
-        }
-
-        pub fn get_default_source_name(&'a self) -> &'a str {
-            get_str(&self.default_source_name)
-        }
-    }
-
-    impl<'a> pa_sink_info {
-        pub fn get_name(&'a self) -> &'a str {
-            get_str(&self.name)
-        }
-
-        pub fn get_description(&'a self) -> &'a str {
-            get_str(&self.description)
-        }
-
-        pub fn get_monitor_source_name(&'a self) -> &'a str {
-            get_str(&self.monitor_source_name)
-        }
-
-        pub fn get_driver(&'a self) -> &'a str {
-            get_str(&self.driver)
-        }
-    }
-
-    /// Turn a raw c pointer with a life time into an &str
-    fn get_str<'a>(c_buf: &'a *const c_char) -> &'a str {
-        let len = unsafe{ strlen(*c_buf) } as usize;
-        let slice: &[c_char] = unsafe{ slice::from_raw_parts(*c_buf, len) };
-        str::from_utf8(unsafe{ mem::transmute(slice) }).unwrap()
-    }
-
 }
 
 /// For types that are just renamed.
 pub mod types {
     pub type pa_volume_t = u32;
     pub type pa_usec_t = u64;
+
+    /// Used by PulseAudio in place of an index to mean "no object"
+    pub const PA_INVALID_INDEX: u32 = 0xffffffff;
 }
@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use bands::Band;
+use scope::Oscilloscope;
+
+
+/// A spectrum's levels in dBFS and the peak markers over them, both per FFT
+/// bin from DC up and per band
+#[derive(Clone, Copy, Debug)]
+pub struct Spectrum<'a> {
+    pub levels: &'a [f64],
+    /// None when peak hold is off
+    pub peaks: Option<&'a [f64]>,
+    /// The level of each of the frame's bands
+    pub band_levels: &'a [f64],
+    pub band_peaks: Option<&'a [f64]>,
+}
+
+
+/// Everything the runner knows about a frame, once it's been through the
+/// gain control and smoothing. The spectra come both per FFT bin and per
+/// band. visualizer::Visualizer draws from the bins, since it fits its bars
+/// to the width of the screen; renderers which don't need that can use the
+/// bands as they are.
+pub struct SpectrumFrame<'a> {
+    /// Which frame this is, counting from 0
+    pub index: usize,
+    /// Seconds of audio from the start of the first frame to the end of
+    /// this one
+    pub time: f64,
+    pub sample_rate: f64,
+    /// The number of samples per FFT. Bin n is at n * sample_rate / fft_size
+    /// Hz.
+    pub fft_size: usize,
+    /// The levels in dBFS to show as empty and full, from the gain control
+    pub range: (f64, f64),
+    /// The edges of the bands, lowest first, set by VizOptions::bands and
+    /// VizOptions::band_count
+    pub bands: &'a [Band],
+    /// The number of channels, each with its own spectrum
+    pub channel_count: usize,
+    /// The combined levels straight out of the FFT, before smoothing and
+    /// the noise gate
+    pub raw: &'a [f64],
+    /// The latest samples of each channel
+    pub scope: &'a Oscilloscope,
+    /// The smoothed levels and peaks of the channels combined and then of
+    /// each channel, one spectrum after another, per bin and per band
+    pub(crate) levels: &'a [f64],
+    pub(crate) peaks: Option<&'a [f64]>,
+    pub(crate) band_levels: &'a [f64],
+    pub(crate) band_peaks: Option<&'a [f64]>,
+}
+
+
+impl<'a> SpectrumFrame<'a> {
+    /// The smoothed spectrum of the channels combined
+    pub fn get_mix(&self) -> Spectrum<'a> {
+        self.get_spectrum(0)
+    }
+
+    /// The smoothed spectrum of a channel, counting from 0
+    pub fn get_channel(&self, channel: usize) -> Spectrum<'a> {
+        assert!(channel < self.channel_count, "no channel {} in a frame of {}", channel, self.channel_count);
+        self.get_spectrum(channel + 1)
+    }
+
+    /// The index'th spectrum in levels, peaks and their bands
+    fn get_spectrum(&self, index: usize) -> Spectrum<'a> {
+        let count = self.channel_count + 1;
+        let part = |values: &'a [f64]| {
+            let size = values.len() / count;
+            &values[index * size..(index + 1) * size]
+        };
+        Spectrum {
+            levels: part(self.levels),
+            peaks: self.peaks.map(part),
+            band_levels: part(self.band_levels),
+            band_peaks: self.band_peaks.map(part),
+        }
+    }
+}
+
+
+/// Something that shows or keeps the frames a VizRunner makes, such as
+/// visualizer::Visualizer drawing them in the terminal. A runner can feed
+/// any number of renderers.
+pub trait Renderer {
+    /// Take the next frame. There's no way to report an error, so a renderer
+    /// that can't show a frame should drop it and carry on with the next.
+    fn render(&mut self, frame: &SpectrumFrame);
+}
+
+
+/// Ignores every frame, for running the pipeline with nothing to show
+pub struct NullRenderer;
+
+
+impl Renderer for NullRenderer {
+    fn render(&mut self, _: &SpectrumFrame) {}
+}
+
+
+/// A copy of a frame's levels, kept by RecordingRenderer
+#[derive(Clone, Debug)]
+pub struct RecordedFrame {
+    pub index: usize,
+    pub time: f64,
+    pub range: (f64, f64),
+    /// The smoothed levels of the channels combined
+    pub levels: Vec<f64>,
+    pub peaks: Option<Vec<f64>>,
+    /// The smoothed levels of each channel
+    pub channel_levels: Vec<Vec<f64>>,
+    /// The smoothed levels of the channels combined, per band
+    pub band_levels: Vec<f64>,
+}
+
+
+/// Keeps a copy of every frame, for tests and for recording a run without a
+/// terminal. The frames are shared, so they can still be read once the
+/// renderer has been handed to a runner.
+pub struct RecordingRenderer {
+    frames: Rc<RefCell<Vec<RecordedFrame>>>,
+}
+
+
+impl Default for RecordingRenderer {
+    fn default() -> RecordingRenderer {
+        RecordingRenderer::new()
+    }
+}
+
+
+impl RecordingRenderer {
+    pub fn new() -> RecordingRenderer {
+        RecordingRenderer {
+            frames: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// The frames recorded so far, and from then on
+    pub fn get_frames(&self) -> Rc<RefCell<Vec<RecordedFrame>>> {
+        self.frames.clone()
+    }
+}
+
+
+impl Renderer for RecordingRenderer {
+    fn render(&mut self, frame: &SpectrumFrame) {
+        let mix = frame.get_mix();
+        self.frames.borrow_mut().push(RecordedFrame {
+            index: frame.index,
+            time: frame.time,
+            range: frame.range,
+            levels: mix.levels.to_vec(),
+            peaks: mix.peaks.map(|peaks| peaks.to_vec()),
+            channel_levels: (0..frame.channel_count).map(|channel| frame.get_channel(channel).levels.to_vec())
+                .collect(),
+            band_levels: mix.band_levels.to_vec(),
+        });
+    }
+}
@@ -0,0 +1,278 @@
+use std::char;
+use fftw::audio::{AudioFft, FULL_SCALE};
+use fftw::backend::FftBackend;
+use fftw::float::FftFloat;
+use visualizer::{BRAILLE_BASE, BRAILLE_LEFT, BRAILLE_RIGHT};
+
+
+/// The character for a dot when drawing without Unicode
+const ASCII_DOT_CHAR: char = '*';
+
+
+/// How far below zero, as a fraction of full scale, the signal has to go
+/// before a rising zero crossing triggers. Keeps noise around zero from
+/// triggering the scope over and over.
+const TRIGGER_HYSTERESIS: f64 = 0.01;
+
+
+/// Find the first rising zero crossing in samples[..search], where the signal
+/// goes from below -TRIGGER_HYSTERESIS to 0 or above. Without one, returns
+/// search, so the scope runs free on the latest samples.
+pub fn find_trigger(samples: &[f64], search: usize) -> usize {
+    let search = search.min(samples.len());
+    let mut armed = false;
+    for (index, &sample) in samples[..search].iter().enumerate() {
+        if sample < -TRIGGER_HYSTERESIS {
+            armed = true;
+        } else if armed && sample >= 0.0 {
+            return index;
+        }
+    }
+    search
+}
+
+
+/// A grid of dots covering the screen. With Unicode, each cell holds a 2 by
+/// 4 Braille pattern of dots; otherwise each cell is a single dot.
+pub struct Canvas {
+    /// The size in cells
+    width: usize,
+    height: usize,
+    /// Whether cells are Braille patterns
+    braille: bool,
+    /// Whether each dot is set, a row at a time from the bottom
+    dots: Vec<bool>,
+}
+
+
+impl Canvas {
+    /// Create an empty canvas of width by height cells
+    pub fn new(width: usize, height: usize, braille: bool) -> Canvas {
+        let (columns, rows) = if braille { (2, 4) } else { (1, 1) };
+        Canvas {
+            width: width,
+            height: height,
+            braille: braille,
+            dots: vec![false; width * columns * height * rows],
+        }
+    }
+
+    /// The size in dots
+    pub fn get_dot_size(&self) -> (usize, usize) {
+        if self.braille { (self.width * 2, self.height * 4) } else { (self.width, self.height) }
+    }
+
+    /// The width of a dot over its height on the screen, taking terminal
+    /// cells to be twice as tall as they are wide
+    pub fn get_dot_aspect(&self) -> f64 {
+        if self.braille { 1.0 } else { 0.5 }
+    }
+
+    /// Whether the dot at column x and row y, counting up from the bottom, is
+    /// set
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let (dot_width, _) = self.get_dot_size();
+        self.dots[y * dot_width + x]
+    }
+
+    /// Set the dot at column x and row y, counting up from the bottom.
+    /// Positions in dots may be fractional, and are rounded. Dots off the
+    /// canvas are ignored.
+    pub fn plot(&mut self, x: f64, y: f64) {
+        let (dot_width, dot_height) = self.get_dot_size();
+        let (x, y) = (x.round(), y.round());
+        if x >= 0.0 && y >= 0.0 && (x as usize) < dot_width && (y as usize) < dot_height {
+            self.dots[y as usize * dot_width + x as usize] = true;
+        }
+    }
+
+    /// Set a line of dots from one position to another, so steep parts of a
+    /// waveform stay joined up
+    pub fn line(&mut self, from: (f64, f64), to: (f64, f64)) {
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0);
+        for step in 0..steps as usize + 1 {
+            let t = step as f64 / steps;
+            self.plot(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        }
+    }
+
+    /// The character for the cell at column x and row y, counting up from
+    /// the bottom
+    pub fn get_char(&self, x: usize, y: usize) -> char {
+        if !self.braille {
+            return if self.get(x, y) { ASCII_DOT_CHAR } else { ' ' };
+        }
+        let mut bits = 0;
+        for (side, dots) in [BRAILLE_LEFT, BRAILLE_RIGHT].iter().enumerate() {
+            for (row, dot) in dots.iter().enumerate() {
+                if self.get(x * 2 + side, y * 4 + row) {
+                    bits |= *dot;
+                }
+            }
+        }
+        char::from_u32(BRAILLE_BASE + bits).unwrap()
+    }
+}
+
+
+/// Keeps the latest samples of each channel, and draws them as a waveform or
+/// as a goniometer
+pub struct Oscilloscope {
+    /// The latest samples of each channel, oldest first, from -1 to 1
+    channels: Vec<Vec<f64>>,
+}
+
+
+impl Default for Oscilloscope {
+    fn default() -> Oscilloscope {
+        Oscilloscope::new()
+    }
+}
+
+
+impl Oscilloscope {
+    pub fn new() -> Oscilloscope {
+        Oscilloscope {
+            channels: Vec::new(),
+        }
+    }
+
+    /// Copy the latest fft_size samples of every channel out of an AudioFft
+    pub fn update<T: FftFloat, B: FftBackend<T>>(&mut self, fft: &AudioFft<T, B>) {
+        let (channel_count, size) = (fft.get_channel_count(), fft.get_fft_size());
+        if self.channels.len() != channel_count || self.channels.iter().any(|samples| samples.len() != size) {
+            self.channels = vec![vec![0.0; size]; channel_count];
+        }
+        for (channel, samples) in self.channels.iter_mut().enumerate() {
+            fft.copy_samples(channel, samples);
+            for sample in samples.iter_mut() {
+                *sample /= FULL_SCALE;
+            }
+        }
+    }
+
+    /// The latest samples of each channel, oldest first, from -1 to 1
+    pub fn get_channels(&self) -> &[Vec<f64>] {
+        &self.channels[..]
+    }
+
+    /// Draw each channel's waveform in its own strip, one above the other.
+    /// The newest half of the samples is shown, starting at a rising zero
+    /// crossing found in the older half, so a steady tone stands still. The
+    /// trigger follows the average of the channels, keeping them in step.
+    pub fn draw_waveforms(&self, canvas: &mut Canvas) {
+        let samples = match self.channels.first() {
+            Some(samples) => samples.len(),
+            None => return,
+        };
+        let span = samples / 2;
+        let mix: Vec<f64> = (0..samples)
+            .map(|index| self.channels.iter().map(|channel| channel[index]).sum::<f64>() / self.channels.len() as f64)
+            .collect();
+        let start = find_trigger(&mix, samples - span);
+
+        let (dot_width, dot_height) = canvas.get_dot_size();
+        let strip_height = dot_height as f64 / self.channels.len() as f64;
+        let x_scale = (dot_width as f64 - 1.0) / (span as f64 - 1.0).max(1.0);
+        for (strip, channel) in self.channels.iter().enumerate() {
+            // The first channel goes at the top. Dots are centered on whole
+            // numbers, so the strip's middle is half a dot lower.
+            let middle = dot_height as f64 - strip_height * (strip as f64 + 0.5) - 0.5;
+            let y_scale = (strip_height - 1.0) / 2.0;
+            let position = |index: usize| (index as f64 * x_scale, middle + channel[start + index] * y_scale);
+            for index in 1..span {
+                canvas.line(position(index - 1), position(index));
+            }
+        }
+    }
+
+    /// Draw the first two channels as a goniometer: the mid signal (L+R)/2
+    /// up the screen and the side signal (L-R)/2 across it, so mono sound is
+    /// a vertical line, sound in one channel is a diagonal, and wide stereo
+    /// spreads out sideways. Uses the largest square that fits.
+    pub fn draw_xy(&self, canvas: &mut Canvas) {
+        if self.channels.len() < 2 {
+            return self.draw_waveforms(canvas);
+        }
+        let (dot_width, dot_height) = canvas.get_dot_size();
+        let aspect = canvas.get_dot_aspect();
+        // The square's size in rows of dots, and then in columns
+        let size = (dot_height as f64).min(dot_width as f64 * aspect);
+        let (x_radius, y_radius) = ((size / aspect - 1.0) / 2.0, (size - 1.0) / 2.0);
+        let (x_center, y_center) = ((dot_width as f64 - 1.0) / 2.0, (dot_height as f64 - 1.0) / 2.0);
+        for (&left, &right) in self.channels[0].iter().zip(self.channels[1].iter()) {
+            let mid = (left + right) / 2.0;
+            let side = (left - right) / 2.0;
+            canvas.plot(x_center + side * x_radius, y_center + mid * y_radius);
+        }
+    }
+}
+
+
+/// An oscilloscope holding the given samples
+#[cfg(test)]
+fn test_scope(channels: Vec<Vec<f64>>) -> Oscilloscope {
+    Oscilloscope { channels: channels }
+}
+
+
+#[test]
+fn test_trigger() {
+    use std::f64::consts::PI;
+    // A sine starting past its rising crossing triggers on the next one
+    let sine: Vec<f64> = (0..400).map(|i| (2.0 * PI * (i as f64 + 30.5) / 100.0).sin()).collect();
+    assert_eq!(find_trigger(&sine, 200), 70);
+    // Noise around zero doesn't trigger, and free runs on the latest samples
+    let noise: Vec<f64> = (0..400).map(|i| if i % 2 == 0 { 0.001 } else { -0.001 }).collect();
+    assert_eq!(find_trigger(&noise, 200), 200);
+    assert_eq!(find_trigger(&sine, 1000), 70);
+}
+
+
+#[test]
+fn test_canvas() {
+    let mut canvas = Canvas::new(2, 1, true);
+    assert_eq!(canvas.get_dot_size(), (4, 4));
+    canvas.line((0.0, 0.0), (3.0, 3.0));
+    assert_eq!(canvas.get_char(0, 0), '\u{2860}');
+    assert_eq!(canvas.get_char(1, 0), '\u{280a}');
+    // Off the canvas is ignored
+    canvas.plot(-1.0, 2.0);
+    canvas.plot(4.0, 2.0);
+
+    let mut canvas = Canvas::new(3, 2, false);
+    canvas.line((0.0, 1.0), (2.0, 1.0));
+    let top: Vec<char> = (0..3).map(|x| canvas.get_char(x, 1)).collect();
+    assert_eq!(top, vec!['*', '*', '*']);
+    assert_eq!(canvas.get_char(1, 0), ' ');
+}
+
+
+#[test]
+fn test_waveforms_and_goniometer() {
+    // A square wave in the left channel and silence in the right
+    let square: Vec<f64> = (0..64).map(|i| if (i / 8) % 2 == 0 { -1.0 } else { 1.0 }).collect();
+    let scope = test_scope(vec![square, vec![0.0; 64]]);
+    let mut canvas = Canvas::new(16, 8, false);
+    scope.draw_waveforms(&mut canvas);
+    // The left channel triggers on its first rising edge, so it starts high
+    // at the top of its strip; the right channel is flat along the middle
+    // of its strip
+    assert!(canvas.get(0, 7));
+    assert!((0..16).all(|x| canvas.get(x, 2)));
+    assert!(!(0..16).any(|x| canvas.get(x, 0)));
+
+    // Mono is a vertical line, and one channel alone is a diagonal
+    let ramp: Vec<f64> = (0..64).map(|i| i as f64 / 31.5 - 1.0).collect();
+    let mut canvas = Canvas::new(20, 5, true);
+    test_scope(vec![ramp.clone(), ramp.clone()]).draw_xy(&mut canvas);
+    let (width, height) = canvas.get_dot_size();
+    assert!((0..height).all(|y| canvas.get(width / 2, y) || canvas.get(width / 2 - 1, y)));
+    assert!((0..width).all(|x| (0..height).all(|y| !canvas.get(x, y) || x == 19 || x == 20)));
+
+    let mut canvas = Canvas::new(20, 5, true);
+    test_scope(vec![ramp, vec![0.0; 64]]).draw_xy(&mut canvas);
+    // Full scale in one channel reaches halfway out, from (15, 5) to (24, 14)
+    assert!(canvas.get(15, 5) && canvas.get(20, 10) && canvas.get(24, 14));
+    assert!(!canvas.get(19, 0) && !canvas.get(19, 19));
+}
@@ -0,0 +1,224 @@
+use fftw::float::FftFloat;
+
+
+/// How bars move between frames. Times are in seconds and levels in dB, so
+/// the bars move at the same speed whatever the frame rate.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SmoothingSettings {
+    /// Time constant for rising bars. After this long, a bar has covered 63%
+    /// of the way to a louder level. 0 jumps straight there.
+    pub attack: f64,
+    /// Time constant for falling bars, like attack. Ignored with gravity.
+    pub release: f64,
+    /// Acceleration of falling bars in dB/s², like a ball dropping. 0 uses
+    /// the release time instead.
+    pub gravity: f64,
+    /// How long peak markers stay at the highest recent level before
+    /// dropping. 0 turns them off.
+    pub peak_hold: f64,
+}
+
+
+impl SmoothingSettings {
+    /// No smoothing: bars show each frame as it is
+    pub fn none() -> SmoothingSettings {
+        SmoothingSettings {
+            attack: 0.0,
+            release: 0.0,
+            gravity: 0.0,
+            peak_hold: 0.0,
+        }
+    }
+}
+
+
+impl Default for SmoothingSettings {
+    fn default() -> SmoothingSettings {
+        SmoothingSettings {
+            attack: 0.01,
+            release: 0.15,
+            gravity: 0.0,
+            peak_hold: 0.0,
+        }
+    }
+}
+
+
+/// Smooths levels from frame to frame, and tracks the peak of each.
+pub struct Smoother {
+    settings: SmoothingSettings,
+    levels: Vec<f64>,
+    /// How fast each level is falling under gravity, in dB/s
+    velocities: Vec<f64>,
+    peaks: Vec<f64>,
+    /// How long each peak has been held for, in seconds
+    peak_ages: Vec<f64>,
+    /// How fast each peak is falling after its hold ran out, in dB/s
+    peak_velocities: Vec<f64>,
+}
+
+
+impl Smoother {
+    pub fn new(settings: SmoothingSettings) -> Smoother {
+        Smoother {
+            settings: settings,
+            levels: Vec::new(),
+            velocities: Vec::new(),
+            peaks: Vec::new(),
+            peak_ages: Vec::new(),
+            peak_velocities: Vec::new(),
+        }
+    }
+
+    pub fn get_settings(&self) -> SmoothingSettings {
+        self.settings
+    }
+
+    /// Move the levels toward a new frame, elapsed seconds after the last.
+    /// The first frame, or one of a different length, is taken as is.
+    pub fn update<T: FftFloat>(&mut self, input: &[T], elapsed: f64) {
+        if self.levels.len() != input.len() {
+            self.levels = input.iter().map(|x| x.to_f64()).collect();
+            self.velocities = vec![0.0; input.len()];
+            self.peaks = self.levels.clone();
+            self.peak_ages = vec![0.0; input.len()];
+            self.peak_velocities = vec![0.0; input.len()];
+            return;
+        }
+
+        let settings = self.settings;
+        for (index, value) in input.iter().enumerate() {
+            let target = value.to_f64();
+            let level = &mut self.levels[index];
+            let velocity = &mut self.velocities[index];
+
+            if target >= *level {
+                *level = approach(*level, target, settings.attack, elapsed);
+                *velocity = 0.0;
+            } else if settings.gravity > 0.0 {
+                *level = fall(*level, velocity, settings.gravity, elapsed).max(target);
+            } else {
+                *level = approach(*level, target, settings.release, elapsed);
+            }
+
+            // Peaks follow the level up, then hold before dropping back to it
+            let peak = &mut self.peaks[index];
+            let age = &mut self.peak_ages[index];
+            if *level >= *peak {
+                *peak = *level;
+                *age = 0.0;
+                self.peak_velocities[index] = 0.0;
+            } else {
+                let falling_time = (*age + elapsed - settings.peak_hold).max(0.0).min(elapsed);
+                *age += elapsed;
+                if falling_time > 0.0 {
+                    *peak = if settings.gravity > 0.0 {
+                        fall(*peak, &mut self.peak_velocities[index], settings.gravity, falling_time)
+                    } else {
+                        *level
+                    }.max(*level);
+                }
+            }
+        }
+    }
+
+    /// The smoothed levels
+    pub fn get_levels(&self) -> &[f64] {
+        &self.levels[..]
+    }
+
+    /// Where the peak markers are, or None if peak hold is off
+    pub fn get_peaks(&self) -> Option<&[f64]> {
+        if self.settings.peak_hold > 0.0 { Some(&self.peaks[..]) } else { None }
+    }
+}
+
+
+/// Exponentially approach target with the given time constant
+fn approach(level: f64, target: f64, time_constant: f64, elapsed: f64) -> f64 {
+    if time_constant <= 0.0 {
+        return target;
+    }
+    target + (level - target) * (-elapsed / time_constant).exp()
+}
+
+
+/// Fall for elapsed seconds under constant acceleration, speeding up
+fn fall(level: f64, velocity: &mut f64, gravity: f64, elapsed: f64) -> f64 {
+    let distance = *velocity * elapsed + 0.5 * gravity * elapsed * elapsed;
+    *velocity += gravity * elapsed;
+    level - distance
+}
+
+
+/// Run frames of input through a smoother, split into steps of the given
+/// number of seconds
+#[cfg(test)]
+fn run_smoother(settings: SmoothingSettings, frames: &[(f64, f64)], step: f64) -> Smoother {
+    let mut smoother = Smoother::new(settings);
+    smoother.update(&[-100.0f64], 0.0);
+    for &(level, duration) in frames.iter() {
+        let steps = (duration / step).round() as usize;
+        for _ in 0..steps {
+            smoother.update(&[level], step);
+        }
+    }
+    smoother
+}
+
+
+#[test]
+fn test_attack_and_release() {
+    let settings = SmoothingSettings { attack: 0.1, release: 0.5, gravity: 0.0, peak_hold: 0.0 };
+    // One time constant covers 63% of the way
+    let smoother = run_smoother(settings, &[(0.0, 0.1)], 0.001);
+    assert!((smoother.get_levels()[0] + 100.0 * (-1f64).exp()).abs() < 1e-6);
+    let smoother = run_smoother(settings, &[(0.0, 1.0), (-100.0, 0.5)], 0.001);
+    assert!((smoother.get_levels()[0] + 100.0 * (1.0 - (-1f64).exp())).abs() < 0.01);
+    assert!(smoother.get_peaks().is_none());
+
+    // No smoothing follows the input exactly
+    let smoother = run_smoother(SmoothingSettings::none(), &[(0.0, 0.1), (-50.0, 0.01)], 0.01);
+    assert_eq!(smoother.get_levels(), &[-50.0]);
+}
+
+
+#[test]
+fn test_gravity() {
+    let settings = SmoothingSettings { attack: 0.0, release: 0.0, gravity: 200.0, peak_hold: 0.0 };
+    // Half of g t²: 25dB in the first half second, and stopped at the input
+    let smoother = run_smoother(settings, &[(0.0, 0.1), (-100.0, 0.5)], 0.01);
+    assert!((smoother.get_levels()[0] + 25.0).abs() < 1e-6);
+    let smoother = run_smoother(settings, &[(0.0, 0.1), (-100.0, 2.0)], 0.01);
+    assert_eq!(smoother.get_levels()[0], -100.0);
+}
+
+
+#[test]
+fn test_peak_hold() {
+    let settings = SmoothingSettings { attack: 0.0, release: 0.0, gravity: 0.0, peak_hold: 0.5 };
+    let smoother = run_smoother(settings, &[(-10.0, 0.1), (-60.0, 0.45)], 0.01);
+    assert_eq!(smoother.get_peaks().unwrap(), &[-10.0]);
+    let smoother = run_smoother(settings, &[(-10.0, 0.1), (-60.0, 0.55)], 0.01);
+    assert_eq!(smoother.get_peaks().unwrap(), &[-60.0]);
+
+    // With gravity, peaks fall from where they were held
+    let settings = SmoothingSettings { gravity: 100.0, ..settings };
+    let smoother = run_smoother(settings, &[(-10.0, 0.1), (-60.0, 0.7)], 0.01);
+    assert!((smoother.get_peaks().unwrap()[0] + 12.0).abs() < 1e-6);
+}
+
+
+#[test]
+fn test_frame_rate_independent() {
+    let settings = SmoothingSettings { attack: 0.05, release: 0.2, gravity: 0.0, peak_hold: 0.3 };
+    let frames = [(-20.0, 0.2), (-80.0, 0.4), (-30.0, 0.1), (-90.0, 0.6)];
+    let gravity = SmoothingSettings { gravity: 150.0, ..settings };
+    for &settings in [settings, gravity].iter() {
+        let slow = run_smoother(settings, &frames, 0.05);
+        let fast = run_smoother(settings, &frames, 0.001);
+        assert!((slow.get_levels()[0] - fast.get_levels()[0]).abs() < 1e-6,
+                "{} != {}", slow.get_levels()[0], fast.get_levels()[0]);
+        assert!((slow.get_peaks().unwrap()[0] - fast.get_peaks().unwrap()[0]).abs() < 1e-6);
+    }
+}
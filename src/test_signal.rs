@@ -0,0 +1,12 @@
+/// Helpers shared by the unit tests
+
+
+/// A deterministic, noisy test signal of len values from 0 to 1. Different
+/// seeds give different signals.
+pub fn noise(len: usize, seed: usize) -> Vec<f64> {
+    let mut state = seed as u64 * 2654435761 + 1;
+    (0..len).map(|_| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) as f64 / (1u64 << 31) as f64
+    }).collect()
+}
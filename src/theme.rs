@@ -0,0 +1,195 @@
+use std::str::FromStr;
+
+
+/// A color, 0-255 per channel
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+
+/// What the colors of a theme follow
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorBy {
+    /// Each row is colored by how high it is, so loud bars reach the end of
+    /// the gradient
+    Height,
+    /// Each column is colored by its frequency, lowest to highest
+    Frequency,
+}
+
+
+/// Parses "height" or "frequency"
+impl FromStr for ColorBy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<ColorBy, String> {
+        match name {
+            "height" => Ok(ColorBy::Height),
+            "frequency" => Ok(ColorBy::Frequency),
+            _ => Err(format!("unknown color mode \"{}\"", name)),
+        }
+    }
+}
+
+
+/// The built in color themes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Theme {
+    /// The terminal's own colors
+    Mono,
+    /// Green, through yellow, to red, like a level meter
+    Classic,
+    /// Red through violet
+    Rainbow,
+    /// Dark red, through orange, to pale yellow
+    Fire,
+    /// Deep blue, through teal, to white
+    Ocean,
+}
+
+
+/// Parses a theme name, as given to --theme: "mono", "classic", "rainbow",
+/// "fire" or "ocean"
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Theme, String> {
+        match name {
+            "mono" => Ok(Theme::Mono),
+            "classic" => Ok(Theme::Classic),
+            "rainbow" => Ok(Theme::Rainbow),
+            "fire" => Ok(Theme::Fire),
+            "ocean" => Ok(Theme::Ocean),
+            _ => Err(format!("unknown theme \"{}\"", name)),
+        }
+    }
+}
+
+
+impl Theme {
+    /// The colors the theme's gradient passes through, evenly spaced. Empty
+    /// for Mono.
+    pub fn get_stops(&self) -> &'static [Rgb] {
+        const CLASSIC: [Rgb; 3] = [Rgb(0, 200, 0), Rgb(230, 220, 0), Rgb(230, 0, 0)];
+        const RAINBOW: [Rgb; 6] = [Rgb(230, 0, 0), Rgb(240, 140, 0), Rgb(230, 230, 0),
+                                   Rgb(0, 200, 0), Rgb(0, 120, 240), Rgb(150, 0, 220)];
+        const FIRE: [Rgb; 4] = [Rgb(120, 0, 0), Rgb(230, 40, 0), Rgb(250, 160, 0), Rgb(255, 250, 180)];
+        const OCEAN: [Rgb; 4] = [Rgb(0, 20, 140), Rgb(0, 110, 200), Rgb(0, 200, 190), Rgb(230, 255, 255)];
+        match *self {
+            Theme::Mono => &[],
+            Theme::Classic => &CLASSIC,
+            Theme::Rainbow => &RAINBOW,
+            Theme::Fire => &FIRE,
+            Theme::Ocean => &OCEAN,
+        }
+    }
+
+    /// The color at position t, from 0 to 1, along the gradient
+    pub fn get_color(&self, t: f64) -> Option<Rgb> {
+        let stops = self.get_stops();
+        if stops.is_empty() {
+            return None;
+        }
+        if stops.len() == 1 {
+            return Some(stops[0]);
+        }
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let index = (position.floor() as usize).min(stops.len() - 2);
+        let fraction = position - index as f64;
+        let (a, b) = (stops[index], stops[index + 1]);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * fraction).round() as u8;
+        Some(Rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2)))
+    }
+}
+
+
+/// How many colors the terminal can show
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorDepth {
+    /// No colors at all
+    Mono,
+    /// The 8 basic ANSI colors
+    Eight,
+    /// The xterm 256 color palette
+    Palette256,
+    /// Any RGB color, with the color number being 0xRRGGBB
+    Direct,
+}
+
+
+impl ColorDepth {
+    /// The depth of a terminal with ncurses' COLORS colors
+    pub fn from_color_count(colors: i32) -> ColorDepth {
+        if colors >= 1 << 24 {
+            ColorDepth::Direct
+        } else if colors >= 256 {
+            ColorDepth::Palette256
+        } else if colors >= 8 {
+            ColorDepth::Eight
+        } else {
+            ColorDepth::Mono
+        }
+    }
+
+    /// The closest color number to an RGB color, or None for Mono
+    pub fn get_color_number(&self, color: Rgb) -> Option<i32> {
+        let Rgb(r, g, b) = color;
+        match *self {
+            ColorDepth::Mono => None,
+            ColorDepth::Eight => Some(nearest_basic_color(color)),
+            ColorDepth::Palette256 => {
+                // The 6x6x6 cube starting at 16
+                let level = |c: u8| (c as i32 * 5 + 127) / 255;
+                Some(16 + 36 * level(r) + 6 * level(g) + level(b))
+            },
+            ColorDepth::Direct => Some(((r as i32) << 16) | ((g as i32) << 8) | b as i32),
+        }
+    }
+}
+
+
+/// The ANSI color closest to an RGB color. Color n has red in bit 0, green
+/// in bit 1 and blue in bit 2.
+fn nearest_basic_color(color: Rgb) -> i32 {
+    let Rgb(r, g, b) = color;
+    let brightest = r.max(g).max(b) as i32;
+    if brightest < 64 {
+        return 0;
+    }
+    // Keep each channel that's at least half as bright as the brightest
+    let on = |c: u8| (c as i32) * 2 >= brightest;
+    (on(r) as i32) | ((on(g) as i32) << 1) | ((on(b) as i32) << 2)
+}
+
+
+#[test]
+fn test_gradient() {
+    assert_eq!(Theme::Classic.get_color(0.0), Some(Rgb(0, 200, 0)));
+    assert_eq!(Theme::Classic.get_color(0.5), Some(Rgb(230, 220, 0)));
+    assert_eq!(Theme::Classic.get_color(1.0), Some(Rgb(230, 0, 0)));
+    assert_eq!(Theme::Classic.get_color(0.25), Some(Rgb(115, 210, 0)));
+    assert_eq!(Theme::Classic.get_color(7.0), Theme::Classic.get_color(1.0));
+    assert_eq!(Theme::Mono.get_color(0.5), None);
+}
+
+
+#[test]
+fn test_color_depths() {
+    assert_eq!(ColorDepth::from_color_count(0), ColorDepth::Mono);
+    assert_eq!(ColorDepth::from_color_count(8), ColorDepth::Eight);
+    assert_eq!(ColorDepth::from_color_count(16), ColorDepth::Eight);
+    assert_eq!(ColorDepth::from_color_count(256), ColorDepth::Palette256);
+    assert_eq!(ColorDepth::from_color_count(1 << 24), ColorDepth::Direct);
+
+    let orange = Rgb(250, 160, 0);
+    assert_eq!(ColorDepth::Mono.get_color_number(orange), None);
+    assert_eq!(ColorDepth::Direct.get_color_number(orange), Some(0xfaa000));
+    assert_eq!(ColorDepth::Palette256.get_color_number(orange), Some(16 + 36 * 5 + 6 * 3));
+    assert_eq!(ColorDepth::Palette256.get_color_number(Rgb(255, 255, 255)), Some(231));
+
+    // Degrading to 8 colors keeps the theme recognizable
+    let basic: Vec<i32> = Theme::Classic.get_stops().iter()
+        .map(|&color| ColorDepth::Eight.get_color_number(color).unwrap()).collect();
+    assert_eq!(basic, vec![2, 3, 1]);
+    assert_eq!(ColorDepth::Eight.get_color_number(Rgb(0, 20, 140)), Some(4));
+    assert_eq!(ColorDepth::Eight.get_color_number(Rgb(10, 10, 10)), Some(0));
+}
@@ -1,81 +1,288 @@
 extern crate libc;
 
-use self::libc::{c_int, c_char};
-use ncurses::window::Window;
+use std::char;
+use std::str::FromStr;
+use self::libc::c_int;
+use ncurses::window::{self, Window};
+use bands::{self, Aggregation, BandMapper, BandSettings};
+use layout::{BottomLayout, ChannelBars, Direction, Layout};
+use renderer::{Renderer, SpectrumFrame};
+use fftw::audio::DEFAULT_FLOOR;
+use fftw::float::FftFloat;
+use scope::{Canvas, Oscilloscope};
+use theme::{ColorBy, ColorDepth, Theme};
+use waterfall::{self, Orientation, Waterfall, WaterfallSettings};
 
 
 /// The character to use for a bar
-const BAR_CHAR: c_char = '|' as c_char;
+const BAR_CHAR: char = '|';
+
+
+/// The character to use for peak markers above a bar
+const PEAK_CHAR: char = '-';
 
 
 /// The character to use for rows above the bar
-const EMPTY_CHAR: c_char = ' ' as c_char;
+const EMPTY_CHAR: char = ' ';
+
+
+/// Block elements for the top of a bar, indexed by how many eighths of the
+/// cell it fills
+const BLOCK_CHARS: [char; 9] = [' ', '\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}',
+                                '\u{2585}', '\u{2586}', '\u{2587}', '\u{2588}'];
+
+
+/// Peak markers for block bars: the upper eighth block
+const BLOCK_PEAK_CHAR: char = '\u{2594}';
+
+
+/// Block elements for the end of a bar growing down, by how many eighths of
+/// the cell it fills. Only the upper eighth and half exist, so this is
+/// coarser than BLOCK_CHARS.
+const BLOCK_DOWN_CHARS: [char; 9] = [' ', '\u{2594}', '\u{2594}', '\u{2580}', '\u{2580}',
+                                     '\u{2580}', '\u{2588}', '\u{2588}', '\u{2588}'];
+
+
+/// Peak markers for block bars growing down: the lower eighth block
+const BLOCK_DOWN_PEAK_CHAR: char = '\u{2581}';
+
+
+/// The Braille pattern with no dots raised. Each of the 8 dots adds a bit.
+pub(crate) const BRAILLE_BASE: u32 = 0x2800;
+
+
+/// The bits of the dots in the left and right columns of a Braille cell, from
+/// the bottom up
+pub(crate) const BRAILLE_LEFT: [u32; 4] = [0x40, 0x04, 0x02, 0x01];
+pub(crate) const BRAILLE_RIGHT: [u32; 4] = [0x80, 0x20, 0x10, 0x08];
 
 
 /// The character to use where there is a lack of data due to scaling issues.
 /// (If the user sees this character, it is because the visualizer wasn't
 /// properly scaled to the window width)
-const BORDER_CHAR: c_char = ' ' as c_char;
+const BORDER_CHAR: char = ' ';
 
 
 /// The character to initialize the row arrays with.
 /// (This is not the same as EMPTY_CHAR so that it is easy to detect that we
 /// didn't draw some part of the screen. Users should never see this.)
-const INIT_CHAR: c_char = '#' as c_char;
+const INIT_CHAR: char = '#';
+
+
+/// The number of colors a theme's gradient is split into. Each is a color
+/// pair, numbered from 1.
+const GRADIENT_STEPS: usize = 16;
+
+
+/// Which step of the gradient colors the cell at column x and row y, counting
+/// up from the bottom, of a width by height screen
+fn gradient_step(color_by: ColorBy, x: usize, y: usize, width: usize, height: usize) -> usize {
+    let t = match color_by {
+        ColorBy::Height => (y as f64 + 0.5) / height as f64,
+        ColorBy::Frequency => (x as f64 + 0.5) / width as f64,
+    };
+    level_step(t)
+}
 
 
-/// Scales down a vector by averaging the elements between the resulting points
-pub fn scale_fft_output(input: &[f64], new_len: usize) -> Vec<f64> {
-    if new_len >= input.len() {
-        return input.to_vec();
+/// Which step of the gradient colors a cell filled by fraction, from 0 to 1
+fn level_step(fraction: f64) -> usize {
+    ((fraction.max(0.0) * (GRADIENT_STEPS - 1) as f64).round() as usize).min(GRADIENT_STEPS - 1)
+}
+
+
+/// What the visualizer shows
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum View {
+    /// Bars for the spectrum
+    Bars,
+    /// An oscilloscope: each channel's waveform, triggered to stand still
+    Scope,
+    /// A goniometer: the left channel against the right
+    Goniometer,
+    /// A scrolling spectrogram, shading each band by its level over time
+    Waterfall,
+}
+
+
+/// Parses "bars", "scope", "xy" or "waterfall"
+impl FromStr for View {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<View, String> {
+        match name {
+            "bars" => Ok(View::Bars),
+            "scope" => Ok(View::Scope),
+            "xy" => Ok(View::Goniometer),
+            "waterfall" => Ok(View::Waterfall),
+            _ => Err(format!("unknown view \"{}\"", name)),
+        }
     }
+}
 
-    let band_size: usize = input.len() / new_len;
-    assert!(band_size > 0);
-    let mut output: Vec<f64> = Vec::with_capacity(new_len);
 
-    let mut temp_count: usize = 0;
-    let mut sum: f64 = 0.0;
+/// The characters bars are drawn with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BarStyle {
+    /// '|' characters, a row at a time. Works on any terminal.
+    Ascii,
+    /// Unicode block elements, for eighths of a row
+    Blocks,
+    /// Unicode Braille patterns: two bars per column, in quarters of a row
+    Braille,
+}
 
-    for &x in input.iter() {
-        if temp_count >= band_size {
-            let avg: f64 = sum/temp_count as f64;
-            output.push(avg);
-            temp_count = 0;
-            sum = 0.0;
-        } else {
-            sum += x;
-            temp_count+=1;
+
+/// Parses "ascii", "blocks" or "braille"
+impl FromStr for BarStyle {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<BarStyle, String> {
+        match name {
+            "ascii" => Ok(BarStyle::Ascii),
+            "blocks" => Ok(BarStyle::Blocks),
+            "braille" => Ok(BarStyle::Braille),
+            _ => Err(format!("unknown bar style \"{}\"", name)),
         }
     }
+}
+
 
-    if temp_count >= band_size {
-        output.push(sum/temp_count as f64);
+impl BarStyle {
+    /// How many bars fit in a column, and how many steps of height fit in a
+    /// row
+    pub(crate) fn get_resolution(&self) -> (usize, usize) {
+        match *self {
+            BarStyle::Ascii => (1, 1),
+            BarStyle::Blocks => (1, 8),
+            BarStyle::Braille => (2, 4),
+        }
     }
+}
 
-    output
+
+/// How many of a row's steps a bar of the given height fills. Row 0 is the
+/// bottom.
+fn filled_steps(height: usize, row: usize, steps: usize) -> usize {
+    height.saturating_sub(row * steps).min(steps)
 }
 
 
+/// Which step of the row holds the top of the peak, if it's in this row and
+/// above the bar
+fn peak_step(peak: usize, height: usize, row: usize, steps: usize) -> Option<usize> {
+    if peak > height && peak > row * steps && peak <= (row + 1) * steps {
+        Some(peak - row * steps - 1)
+    } else {
+        None
+    }
+}
 
-/// Loops through an iterator of f64 and gets the min and max values.
-/// The min/max functions in the standard library don't work on floats.
-fn get_min_max<'a, I: Iterator<Item=&'a f64>>(iter: &'a mut I) -> (f64, f64) {
-    let mut min: f64 = 0.0;
-    let mut max: f64 = 0.0;
-    for &x in iter {
-        if x < min {
-            min = x;
-        }
-        if x > max {
-            max = x;
+
+/// The character for a cell of bars, at column x and row y counting from
+/// where the bars start. heights and peaks are in steps; see
+/// BarStyle::get_resolution. Bars growing down fill cells from the top.
+pub(crate) fn draw_cell(style: BarStyle, heights: &[usize], peaks: Option<&[usize]>, x: usize, y: usize,
+                        direction: Direction) -> char {
+    let (bars_per_column, steps) = style.get_resolution();
+    if (x + 1) * bars_per_column > heights.len() {
+        return BORDER_CHAR;
+    }
+    let peak = |bar: usize| peaks.and_then(|peaks| peak_step(peaks[bar], heights[bar], y, steps));
+
+    match style {
+        BarStyle::Ascii => {
+            if filled_steps(heights[x], y, steps) > 0 {
+                BAR_CHAR
+            } else if peak(x).is_some() {
+                PEAK_CHAR
+            } else {
+                EMPTY_CHAR
+            }
+        },
+        BarStyle::Blocks => {
+            let filled = filled_steps(heights[x], y, steps);
+            let (chars, peak_char) = match direction {
+                Direction::Up => (&BLOCK_CHARS, BLOCK_PEAK_CHAR),
+                Direction::Down => (&BLOCK_DOWN_CHARS, BLOCK_DOWN_PEAK_CHAR),
+            };
+            if filled == 0 && peak(x).is_some() { peak_char } else { chars[filled] }
+        },
+        BarStyle::Braille => {
+            let mut bits = 0;
+            for (side, dots) in [BRAILLE_LEFT, BRAILLE_RIGHT].iter().enumerate() {
+                let bar = x * 2 + side;
+                let dot = |step: usize| match direction {
+                    Direction::Up => dots[step],
+                    Direction::Down => dots[steps - 1 - step],
+                };
+                for step in 0..filled_steps(heights[bar], y, steps) {
+                    bits |= dot(step);
+                }
+                if let Some(step) = peak(bar) {
+                    bits |= dot(step);
+                }
+            }
+            char::from_u32(BRAILLE_BASE + bits).unwrap()
+        },
+    }
+}
+
+
+/// Resamples a vector to exactly new_len values. Each output value covers an
+/// equal, possibly fractional, share of the input; input values split across
+/// two outputs count toward each by how much of them falls in it. Shrinking
+/// combines those shares with the given aggregation. Growing interpolates
+/// linearly between the input values instead, so a wide screen shows smooth
+/// slopes rather than steps.
+pub fn scale_fft_output(input: &[f64], new_len: usize, aggregation: Aggregation) -> Vec<f64> {
+    if input.is_empty() {
+        return vec![0.0; new_len];
+    }
+    // Input values per output value
+    let ratio = input.len() as f64 / new_len as f64;
+
+    (0..new_len).map(|column| {
+        if ratio <= 1.0 {
+            // Interpolate at the center of the column, in input coordinates
+            let position = ((column as f64 + 0.5) * ratio - 0.5).max(0.0);
+            let index = (position.floor() as usize).min(input.len() - 1);
+            let next = (index + 1).min(input.len() - 1);
+            let fraction = position - index as f64;
+            input[index] * (1.0 - fraction) + input[next] * fraction
+        } else {
+            let start = column as f64 * ratio;
+            let end = start + ratio;
+            let last = (end.ceil() as usize).min(input.len());
+            let overlaps = (start.floor() as usize..last).map(|i| {
+                let weight = end.min(i as f64 + 1.0) - start.max(i as f64);
+                (weight.max(0.0), input[i])
+            });
+            aggregation.combine(overlaps, ratio)
+                .unwrap_or_else(|| bands::interpolate(input, (start + end) / 2.0 - 0.5))
         }
+    }).collect()
+}
+
+
+/// Combine levels in dBFS into count bands, as amplitudes rather than
+/// decibels so Mean and Rms average amplitude and power. Without a band
+/// mapper, the FFT bins are averaged down to count.
+fn to_band_amplitudes<T: FftFloat>(mapper: Option<&BandMapper>, data: &[T], count: usize) -> Vec<f64> {
+    let amplitudes: Vec<f64> = data.iter().map(|x| 10f64.powf(x.to_f64() / 20.0)).collect();
+    match mapper {
+        Some(mapper) => {
+            // Octave bands don't depend on the width, so stretch them out
+            let bands = mapper.map(&amplitudes);
+            if bands.len() == count { bands } else { scale_fft_output(&bands, count, Aggregation::Max) }
+        },
+        None => scale_fft_output(&amplitudes, count, Aggregation::Mean),
     }
-    (min, max)
 }
 
+
 /// Resize the row buffer to width
-fn resize_rowbuf(row: &mut Vec<c_char>, width: usize) {
+fn resize_rowbuf(row: &mut Vec<char>, width: usize) {
     while row.len() < width {
         row.push(INIT_CHAR);
     }
@@ -90,11 +297,31 @@ pub struct Visualizer{
    win: Window,
    // A buffer of characters for a row on the screen (used to reduce calls to
    // the ncurses addstr function)
-   rows: Vec<Vec<c_char>>,
+   rows: Vec<Vec<char>>,
    // The width of the window the last time the animation was called
    width: usize,
    // The height of the window the last time the animation was called
-   height: usize
+   height: usize,
+   // The levels in dBFS drawn as an empty column and a full one
+   range: (f64, f64),
+   // Which frequencies to draw, with the sample rate and FFT size the FFT
+   // output comes from. None averages the FFT bins down to the width.
+   band_settings: Option<(BandSettings, f64, usize)>,
+   // Maps the FFT bins onto one band per bar at the current width
+   band_mapper: Option<BandMapper>,
+   // Maps the FFT bins onto one band per line of the waterfall, with the
+   // number of lines it was made for
+   waterfall_mapper: Option<(usize, BandMapper)>,
+   // What's drawn for each frame
+   view: View,
+   // The history drawn by the Waterfall view
+   waterfall: Waterfall,
+   // Where the bars go on the screen
+   layout: Box<dyn Layout>,
+   // The characters bars are drawn with
+   style: BarStyle,
+   // What the colors follow, or None to use the terminal's colors
+   color_by: Option<ColorBy>
 }
 
 
@@ -114,7 +341,16 @@ impl Visualizer {
             win: win,
             rows: Vec::new(),
             width: 0,
-            height: 0
+            height: 0,
+            range: (DEFAULT_FLOOR, 0.0),
+            band_settings: None,
+            band_mapper: None,
+            waterfall_mapper: None,
+            view: View::Bars,
+            waterfall: Waterfall::new(WaterfallSettings::default()),
+            layout: Box::new(BottomLayout),
+            style: BarStyle::Ascii,
+            color_by: None
         }
     }
 
@@ -125,6 +361,127 @@ impl Visualizer {
         self.win.get_max_x().unwrap() as usize - 1
     }
 
+    /// Set the levels in dBFS drawn as an empty column and a full one. By
+    /// default, that's from the FFT's default floor up to 0dBFS. See
+    /// gain::GainControl for picking the range as the music changes.
+    pub fn set_range(&mut self, bottom: f64, top: f64) {
+        self.range = (bottom, top);
+    }
+
+    /// Draw bands of frequencies instead of equal slices of the FFT output.
+    /// The FFT output comes from FFTs of fft_size points at sample_rate.
+    pub fn set_bands(&mut self, settings: BandSettings, sample_rate: f64, fft_size: usize) {
+        self.band_settings = Some((settings, sample_rate, fft_size));
+        self.update_band_mapper();
+    }
+
+    /// Draw bars with the given style, or ASCII if the terminal can't show
+    /// Unicode. Returns the style used.
+    pub fn set_bar_style(&mut self, style: BarStyle) -> BarStyle {
+        self.style = if window::supports_unicode() { style } else { BarStyle::Ascii };
+        self.update_band_mapper();
+        self.style
+    }
+
+    /// Show something other than bars when used as a Renderer
+    pub fn set_view(&mut self, view: View) {
+        self.view = view;
+    }
+
+    /// Change how the waterfall is drawn. This starts its history over.
+    pub fn set_waterfall(&mut self, settings: WaterfallSettings) {
+        self.waterfall = Waterfall::new(settings);
+    }
+
+    /// Arrange the bars with a different layout. The default is
+    /// layout::BottomLayout.
+    pub fn set_layout(&mut self, layout: Box<dyn Layout>) {
+        self.layout = layout;
+        self.update_band_mapper();
+    }
+
+    /// How many channels of levels the layout shows. See render_channels.
+    pub fn get_channel_count(&self) -> usize {
+        self.layout.get_channel_count()
+    }
+
+    /// Color the bars with a theme's gradient, following their height or
+    /// frequency. Themes are drawn with as many colors as the terminal has,
+    /// down to the 8 basic colors. Returns the color depth used, which is
+    /// Mono for Theme::Mono or a terminal without colors.
+    pub fn set_theme(&mut self, theme: Theme, color_by: ColorBy) -> ColorDepth {
+        self.color_by = None;
+        if theme == Theme::Mono {
+            return ColorDepth::Mono;
+        }
+        let depth = match window::start_color() {
+            Ok(colors) => ColorDepth::from_color_count(colors),
+            Err(_) => return ColorDepth::Mono,
+        };
+        // Draw over the terminal's background if it lets us
+        let background = if window::use_default_colors().is_ok() { -1 } else { 0 };
+        for step in 0..GRADIENT_STEPS {
+            let color = theme.get_color(step as f64 / (GRADIENT_STEPS - 1) as f64).unwrap();
+            let number = match depth.get_color_number(color) {
+                Some(number) => number,
+                None => return ColorDepth::Mono,
+            };
+            if window::init_pair(step as i16 + 1, number, background).is_err() {
+                return ColorDepth::Mono;
+            }
+        }
+        self.color_by = Some(color_by);
+        depth
+    }
+
+    /// Write a row to the screen, changing colors along the way if there's a
+    /// theme. y counts up from the bottom.
+    fn draw_row(&mut self, y: usize) -> Result<c_int, c_int> {
+        let color_by = match self.color_by {
+            Some(color_by) => color_by,
+            None => return self.win.addchars((self.height - y - 1) as c_int, 0, &self.rows[y]),
+        };
+        let (width, height) = (self.width, self.height);
+        let steps: Vec<usize> = (0..self.rows[y].len()).map(|x| gradient_step(color_by, x, y, width, height)).collect();
+        self.draw_colored_row(y, &steps)
+    }
+
+    /// Write a row to the screen with each cell colored by a step of the
+    /// theme's gradient
+    fn draw_colored_row(&mut self, y: usize, steps: &[usize]) -> Result<c_int, c_int> {
+        let screen_y = (self.height - y - 1) as c_int;
+
+        // Write each run of cells of the same color at once
+        let mut start = 0;
+        while start < self.rows[y].len() {
+            let step = steps[start];
+            let mut end = start + 1;
+            while end < self.rows[y].len() && steps[end] == step {
+                end += 1;
+            }
+            let pair = window::color_pair(step as i16 + 1);
+            self.win.attron(pair)?;
+            let result = self.win.addchars(screen_y, start as c_int, &self.rows[y][start..end]);
+            self.win.attroff(pair)?;
+            result?;
+            start = end;
+        }
+        Ok(0)
+    }
+
+    /// The number of bars each channel has across the screen
+    fn get_bar_count(&self) -> usize {
+        self.layout.get_bar_area(self.width, self.height).0 * self.style.get_resolution().0
+    }
+
+    /// Split the bands again for the current width
+    fn update_band_mapper(&mut self) {
+        let bar_count = self.get_bar_count();
+        self.band_mapper = self.band_settings.map(|(settings, sample_rate, fft_size)| {
+            BandMapper::new(settings, bar_count, sample_rate, fft_size)
+        });
+    }
+
     /// Adds or removes rows if the window size is changed.
     fn update_row_count(&mut self, height: usize) {
         while self.rows.len() < height {
@@ -154,40 +511,68 @@ impl Visualizer {
             self.resize_rowbufs(width);
             self.width = width;
             self.height = height;
+            self.update_band_mapper();
         }
     }
 
-    /// Render a single frame of the animation
-    pub fn render_frame(&mut self, data: &[f64]) -> Result<(), c_int> {
+    /// Turn levels in dBFS into the height of each bar, in steps of the bar
+    /// style's resolution
+    fn to_bar_heights<T: FftFloat>(&self, data: &[T]) -> Vec<usize> {
+        let amplitudes = to_band_amplitudes(self.band_mapper.as_ref(), data, self.get_bar_count());
+        let (bottom, top) = self.range;
+        let rows = self.layout.get_bar_area(self.width, self.height).1;
+        let max_bar = (rows * self.style.get_resolution().1) as f64;
+        amplitudes.iter()
+            .map(|a| {
+                let fraction = (20.0 * a.log10() - bottom) / (top - bottom);
+                (fraction * max_bar).max(0.0).min(max_bar) as usize
+            })
+            .collect()
+    }
+
+    /// Render a single frame of the animation from levels in dBFS. The FFT
+    /// output may be either precision; drawing is always done in f64.
+    pub fn render_frame<T: FftFloat>(&mut self, data: &[T]) -> Result<(), c_int> {
+        self.render_frame_with_peaks(data, None)
+    }
+
+    /// Render a frame with a peak marker above each bar, at the height of
+    /// the peak levels in dBFS. See smoothing::Smoother. Layouts showing two
+    /// channels show the frame in both.
+    pub fn render_frame_with_peaks<T: FftFloat>(&mut self, data: &[T], peaks: Option<&[T]>) -> Result<(), c_int> {
+        let peaks = peaks.map(|peaks| [peaks]);
+        self.render_channels(&[data], peaks.as_ref().map(|peaks| &peaks[..]))
+    }
+
+    /// Render a frame of levels in dBFS for each channel the layout shows,
+    /// with optional peak levels for each. Missing channels repeat the last
+    /// one given.
+    pub fn render_channels<T: FftFloat>(&mut self, channels: &[&[T]], peaks: Option<&[&[T]]>) -> Result<(), c_int> {
         self.update_size();
 
-        let data = scale_fft_output(data, self.width as usize);
-        let (_, max_val) = get_min_max(&mut data.iter());
-        let scaled: Vec<usize> = data.iter()
-            .map(|&x| {
-                if x < 1.0 {
-                    0
-                } else {
-                    ((x / max_val) * (self.height as f64 - 1.0)) as usize
-                }
-            })
-            .collect();
-
-        for (y, row) in self.rows.iter_mut().enumerate().rev() {
-            for (x, val) in row.iter_mut().enumerate() {
-                *val = if x >= scaled.len() {
-                    BORDER_CHAR
-                } else {
-                    let val = scaled[x];
-                    if val >= y {
-                        BAR_CHAR
-                    } else {
-                        EMPTY_CHAR
-                    }
-                };
+        let bars: Vec<ChannelBars> = (0..self.layout.get_channel_count()).map(|channel| {
+            let channel = channel.min(channels.len() - 1);
+            ChannelBars {
+                heights: self.to_bar_heights(channels[channel]),
+                peaks: peaks.map(|peaks| self.to_bar_heights(peaks[channel])),
             }
-
-            match self.win.addbytes((self.height - y -1) as c_int, 0, row) {
+        }).collect();
+        self.layout.fill_rows(self.style, &bars, &mut self.rows);
+
+        let (width, height) = (self.width, self.height);
+        for y in (0..self.rows.len()).rev() {
+            let result = match self.color_by {
+                Some(color_by) => {
+                    // Color along the bars however the layout arranges them
+                    let steps: Vec<usize> = (0..width).map(|x| {
+                        let (across, up) = self.layout.get_bar_position(x, y, width, height);
+                        level_step(if color_by == ColorBy::Height { up } else { across })
+                    }).collect();
+                    self.draw_colored_row(y, &steps)
+                },
+                None => self.draw_row(y),
+            };
+            match result {
                 Err(_) => {
                     // Happens when window is resized. Skip the frame.
                     return Ok(());
@@ -197,7 +582,7 @@ impl Visualizer {
         }
 
         // Add some info so you can see the decisions it's making
-        let debuginfo = format!(" width: {}, height: {}, bars: {} ", self.width, self.height, scaled.len());
+        let debuginfo = format!(" width: {}, height: {}, bars: {} ", self.width, self.height, bars[0].heights.len());
         let _ = self.win.addstr(0, (self.width - debuginfo.len()) as c_int, &debuginfo[..]);
 
         // Calling refresh makes it actually take effect
@@ -205,7 +590,209 @@ impl Visualizer {
 
         Ok(())
     }
+
+    /// Add a frame of levels in dBFS to the waterfall, as one band per line
+    /// across the screen, and draw its history. See set_waterfall.
+    pub fn render_waterfall<T: FftFloat>(&mut self, data: &[T]) -> Result<(), c_int> {
+        self.update_size();
+
+        let settings = self.waterfall.get_settings();
+        let (band_count, line_count) = match settings.orientation {
+            Orientation::Vertical => (self.width, self.height),
+            Orientation::Horizontal => (self.height, self.width),
+        };
+        if self.waterfall_mapper.as_ref().map_or(true, |&(count, _)| count != band_count) {
+            self.waterfall_mapper = self.band_settings.map(|(settings, sample_rate, fft_size)| {
+                (band_count, BandMapper::new(settings, band_count, sample_rate, fft_size))
+            });
+        }
+        let levels = to_band_amplitudes(self.waterfall_mapper.as_ref().map(|&(_, ref mapper)| mapper),
+                                        data, band_count)
+            .iter().map(|a| 20.0 * a.log10()).collect();
+        let capacity = if settings.history > 0 { settings.history } else { line_count };
+        self.waterfall.push(levels, capacity);
+
+        let (bottom, top) = settings.range.unwrap_or(self.range);
+        let lines = self.waterfall.get_lines(line_count, capacity, bottom);
+        let unicode = self.style != BarStyle::Ascii;
+        let mut steps = vec![0; self.width];
+        for y in (0..self.rows.len()).rev() {
+            for (x, val) in self.rows[y].iter_mut().enumerate() {
+                // The oldest line is at the bottom or on the left
+                let level = match settings.orientation {
+                    Orientation::Vertical => lines[y][x],
+                    Orientation::Horizontal => lines[x][y],
+                };
+                let fraction = (level - bottom) / (top - bottom);
+                *val = waterfall::shade_char(fraction, unicode);
+                steps[x] = level_step(fraction);
+            }
+            let result = if self.color_by.is_some() {
+                self.draw_colored_row(y, &steps)
+            } else {
+                self.draw_row(y)
+            };
+            if result.is_err() {
+                // Happens when window is resized. Skip the frame.
+                return Ok(());
+            }
+        }
+
+        self.win.refresh()?;
+        Ok(())
+    }
+
+    /// Render an oscilloscope's samples, as a goniometer for the Goniometer
+    /// view and as waveforms otherwise. Unicode bar styles draw with Braille
+    /// dots, for finer lines.
+    pub fn render_scope(&mut self, scope: &Oscilloscope) -> Result<(), c_int> {
+        self.update_size();
+
+        let mut canvas = Canvas::new(self.width, self.height, self.style != BarStyle::Ascii);
+        match self.view {
+            View::Goniometer => scope.draw_xy(&mut canvas),
+            _ => scope.draw_waveforms(&mut canvas),
+        }
+
+        for y in (0..self.rows.len()).rev() {
+            for (x, val) in self.rows[y].iter_mut().enumerate() {
+                *val = canvas.get_char(x, y);
+            }
+            if self.draw_row(y).is_err() {
+                // Happens when window is resized. Skip the frame.
+                return Ok(());
+            }
+        }
+
+        self.win.refresh()?;
+        Ok(())
+    }
+}
+
+
+/// Draws each frame with the current view
+impl Renderer for Visualizer {
+    fn render(&mut self, frame: &SpectrumFrame) {
+        self.set_range(frame.range.0, frame.range.1);
+        // An ncurses error, such as drawing while the terminal is being
+        // resized, only costs this frame
+        let _ = match self.view {
+            View::Bars if self.get_channel_count() == 2 && frame.channel_count > 0 => {
+                // Mono input shows in both
+                let (left, right) = (frame.get_channel(0), frame.get_channel(frame.channel_count - 1));
+                let peaks = match (left.peaks, right.peaks) {
+                    (Some(left), Some(right)) => Some([left, right]),
+                    _ => None,
+                };
+                self.render_channels(&[left.levels, right.levels], peaks.as_ref().map(|peaks| &peaks[..]))
+            },
+            View::Bars => {
+                let mix = frame.get_mix();
+                self.render_frame_with_peaks(mix.levels, mix.peaks)
+            },
+            View::Scope | View::Goniometer => self.render_scope(frame.scope),
+            // The raw levels, so short whistles and steady hum both show up
+            View::Waterfall => self.render_waterfall(frame.raw),
+        };
+    }
 }
 
 
 unsafe impl Send for Visualizer {}
+
+
+#[cfg(test)]
+use test_signal::noise;
+
+
+#[test]
+fn test_scale_fills_width() {
+    for len in 1..70 {
+        let input = noise(len, len);
+        for new_len in 1..150 {
+            for &aggregation in [Aggregation::Max, Aggregation::Mean, Aggregation::Rms].iter() {
+                let output = scale_fft_output(&input, new_len, aggregation);
+                assert_eq!(output.len(), new_len);
+                // Neither shrinking nor interpolating leaves the input's range
+                let min = input.iter().cloned().fold(1.0, f64::min);
+                let max = input.iter().cloned().fold(0.0, f64::max);
+                assert!(output.iter().all(|&x| x >= min - 1e-9 && x <= max + 1e-9),
+                        "{} -> {} {:?}", len, new_len, aggregation);
+            }
+        }
+    }
+}
+
+
+#[test]
+fn test_scale_conserves_energy() {
+    for seed in 0..20 {
+        let len = 100 + seed * 37;
+        let input = noise(len, seed);
+        let sum: f64 = input.iter().sum();
+        let energy: f64 = input.iter().map(|x| x * x).sum();
+        for &new_len in [1, 7, 33, 80, 99].iter() {
+            // Each output stands for len/new_len inputs
+            let ratio = len as f64 / new_len as f64;
+            let mean = scale_fft_output(&input, new_len, Aggregation::Mean);
+            assert!((mean.iter().sum::<f64>() * ratio - sum).abs() < 1e-9 * sum);
+            let rms = scale_fft_output(&input, new_len, Aggregation::Rms);
+            assert!((rms.iter().map(|x| x * x).sum::<f64>() * ratio - energy).abs() < 1e-9 * energy);
+            // Max never misses a peak
+            let max = scale_fft_output(&input, new_len, Aggregation::Max);
+            let peak = input.iter().cloned().fold(0.0, f64::max);
+            assert_eq!(max.iter().cloned().fold(0.0, f64::max), peak);
+        }
+
+        // Interpolating keeps the total, give or take the half sample at each
+        // end
+        let new_len = len * 3 + seed;
+        let ratio = len as f64 / new_len as f64;
+        let upsampled = scale_fft_output(&input, new_len, Aggregation::Mean);
+        let edges = (input[0] + input[len - 1]) / 2.0 * ratio;
+        assert!((upsampled.iter().sum::<f64>() * ratio - sum).abs() < edges);
+    }
+}
+
+
+#[test]
+fn test_scale_keeps_every_bin() {
+    // The old version dropped the first value of every band after the first
+    let input = [1.0, 1.0, 5.0, 1.0, 9.0, 1.0];
+    assert_eq!(scale_fft_output(&input, 3, Aggregation::Max), vec![1.0, 5.0, 9.0]);
+    assert_eq!(scale_fft_output(&input, 3, Aggregation::Mean), vec![1.0, 3.0, 5.0]);
+    assert_eq!(scale_fft_output(&[2.0, 4.0], 4, Aggregation::Mean), vec![2.0, 2.5, 3.5, 4.0]);
+}
+
+
+#[test]
+fn test_draw_cells() {
+    // A bar 2.5 rows tall in each style, with its peak a row higher
+    let draw = |style: BarStyle, heights: &[usize], peaks: &[usize]| -> Vec<char> {
+        (0..5).map(|y| draw_cell(style, heights, Some(peaks), 0, y, Direction::Up)).collect()
+    };
+    assert_eq!(draw(BarStyle::Ascii, &[2], &[4]), vec!['|', '|', ' ', '-', ' ']);
+    assert_eq!(draw(BarStyle::Blocks, &[20], &[28]),
+               vec!['\u{2588}', '\u{2588}', '\u{2584}', '\u{2594}', ' ']);
+
+    // Left bar 1.5 rows, right bar a quarter row with its peak at 2.25
+    assert_eq!(draw(BarStyle::Braille, &[6, 1], &[6, 9]),
+               vec!['\u{28c7}', '\u{2844}', '\u{2880}', '\u{2800}', '\u{2800}']);
+
+    // Columns without a bar are borders
+    assert_eq!(draw_cell(BarStyle::Braille, &[1, 2, 3], None, 1, 0, Direction::Up), BORDER_CHAR);
+}
+
+
+#[test]
+fn test_gradient_steps() {
+    // Rows from the bottom to the top of the screen cover the whole gradient
+    let steps: Vec<usize> = (0..32).map(|y| gradient_step(ColorBy::Height, 5, y, 80, 32)).collect();
+    assert_eq!(steps[0], 0);
+    assert_eq!(steps[31], GRADIENT_STEPS - 1);
+    assert!(steps.windows(2).all(|pair| pair[0] <= pair[1]));
+    // And so do columns from left to right
+    assert_eq!(gradient_step(ColorBy::Frequency, 0, 5, 80, 32), 0);
+    assert_eq!(gradient_step(ColorBy::Frequency, 79, 5, 80, 32), GRADIENT_STEPS - 1);
+    assert_eq!(gradient_step(ColorBy::Frequency, 40, 0, 80, 32), gradient_step(ColorBy::Frequency, 40, 31, 80, 32));
+}
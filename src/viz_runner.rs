@@ -1,34 +1,210 @@
 extern crate libc;
 
-use self::libc::{c_int, size_t};
+use self::libc::size_t;
 use std::rc::Rc;
 use std::cell::RefCell;
 
-use fftw::audio::AudioFft;
-use pulse::{Context, PulseAudioMainloop, PulseAudioStream};
+use bands::{BandMapper, BandSettings};
+use gain::{GainControl, GainSettings};
+use layout::BarLayout;
+use scope::Oscilloscope;
+use smoothing::{Smoother, SmoothingSettings};
+use fftw::audio::{AudioFft, ChannelMix, DEFAULT_FLOOR};
+use fftw::backend::FftError;
+use fftw::float::FftFloat;
+use fftw::types::PlannerFlags;
+use fftw::window::WindowFunction;
+use pulse::{Context, PulseAudioMainloop};
+use pulse::backend::{Backend, BackendStream};
+use pulse::subscription_manager::{EventFacility, EventKind};
 use pulse::types::*;
+use renderer::{Renderer, SpectrumFrame};
 use visualizer;
+use theme::{ColorBy, Theme};
+use waterfall::WaterfallSettings;
+use visualizer::{BarStyle, View};
+
+
+/// The rate the runner records at, in Hz. Frequencies at or above half of it
+/// can't be shown.
+pub const SAMPLE_RATE: u32 = 44100;
 
 
 const DEFAULT_SAMPLE_SPEC: pa_sample_spec = pa_sample_spec {
     format:pa_sample_format::PA_SAMPLE_S16LE,
-    rate: 44100,
+    rate: SAMPLE_RATE,
     channels: 2
 };
 
 
+/// Settings for a VizRunner
+#[derive(Clone, Copy, Debug)]
+pub struct VizOptions {
+    /// How hard FFTW should look for a fast plan. Anything above Measure is
+    /// only worth it with wisdom saved between runs; see fftw::wisdom.
+    pub plan_effort: PlannerFlags,
+    /// The number of samples per FFT. FFTW handles any size, so sizes like
+    /// 4800 (100ms at 48kHz) can be used to put the bins on round
+    /// frequencies; the pure Rust FFT needs a power of two.
+    pub fft_size: usize,
+    /// Samples between FFTs. The FFTs overlap when this is less than
+    /// fft_size, which gives more frames per second. None means half of
+    /// fft_size: about 86 frames per second for 1024 samples at 44.1kHz.
+    pub hop_size: Option<usize>,
+    /// The window applied before each FFT
+    pub window: WindowFunction,
+    /// The quietest level the FFT reports, in dBFS
+    pub floor: f64,
+    /// How the channels are combined into one spectrum
+    pub channel_mix: ChannelMix,
+    /// Which frequencies the bars show and how they are spaced
+    pub bands: BandSettings,
+    /// How many bands the frames handed to renderers have. The terminal fits
+    /// its bars to the screen instead. Octave and third octave scales ignore
+    /// this.
+    pub band_count: usize,
+    /// How the bars move between frames
+    pub smoothing: SmoothingSettings,
+    /// The range of levels shown, and the noise gate
+    pub gain: GainSettings,
+    /// Whether to show the spectrum, its history or the waveform
+    pub view: View,
+    /// How the waterfall view is drawn
+    pub waterfall: WaterfallSettings,
+    /// Where the bars go on the screen
+    pub layout: BarLayout,
+    /// The characters bars are drawn with. Falls back to ASCII if the
+    /// terminal can't show Unicode.
+    pub bar_style: BarStyle,
+    /// The colors of the bars
+    pub theme: Theme,
+    /// Whether the theme's colors follow the bars' height or frequency
+    pub color_by: ColorBy,
+}
+
+
+impl Default for VizOptions {
+    fn default() -> VizOptions {
+        VizOptions {
+            plan_effort: PlannerFlags::Measure,
+            fft_size: 1024,
+            hop_size: None,
+            window: WindowFunction::Hann,
+            floor: DEFAULT_FLOOR,
+            channel_mix: ChannelMix::Max,
+            bands: BandSettings::default(),
+            band_count: 32,
+            smoothing: SmoothingSettings::default(),
+            gain: GainSettings::default(),
+            view: View::Bars,
+            waterfall: WaterfallSettings::default(),
+            layout: BarLayout::Bottom,
+            bar_style: BarStyle::Blocks,
+            theme: Theme::Classic,
+            color_by: ColorBy::Height,
+        }
+    }
+}
+
+
+/// Make the FFT for a runner
+fn create_fft(options: &VizOptions) -> Result<AudioFft, FftError> {
+    let mut fft: AudioFft = AudioFft::with_flags(options.fft_size, DEFAULT_SAMPLE_SPEC.channels as usize,
+                                                 options.plan_effort)?;
+    let hop_size = options.hop_size.unwrap_or(options.fft_size / 2);
+    fft.set_hop_size(if hop_size > 0 { hop_size } else { 1 });
+    fft.set_window(options.window);
+    fft.set_floor(options.floor);
+    fft.set_channel_mix(options.channel_mix);
+    Ok(fft)
+}
+
+
+/// Make a terminal visualizer drawing the way the options say. Takes over the
+/// terminal.
+pub fn create_visualizer(options: &VizOptions) -> visualizer::Visualizer {
+    let mut viz = visualizer::Visualizer::new();
+    viz.set_bands(options.bands, DEFAULT_SAMPLE_SPEC.rate as f64, options.fft_size);
+    viz.set_bar_style(options.bar_style);
+    viz.set_layout(options.layout.create());
+    viz.set_theme(options.theme, options.color_by);
+    viz.set_view(options.view);
+    viz.set_waterfall(options.waterfall);
+    viz
+}
+
+
+/// Map spectra of levels in dBFS, each bins long and one after another, onto
+/// the bands, adding the band levels to output. Like the bars, bands combine
+/// amplitudes rather than decibels. amplitudes is scratch space.
+fn map_bands(mapper: &BandMapper, levels: &[f64], bins: usize, amplitudes: &mut Vec<f64>, output: &mut Vec<f64>) {
+    for spectrum in levels.chunks(bins.max(1)) {
+        amplitudes.clear();
+        amplitudes.extend(spectrum.iter().map(|level| 10f64.powf(level / 20.0)));
+        let start = output.len();
+        mapper.map_into(amplitudes, output);
+        for level in output[start..].iter_mut() {
+            *level = 20.0 * level.log10();
+        }
+    }
+}
+
+
 #[derive(Clone)]
 /// The culmination of all of the visualizer parts
-pub struct VizRunner<'a> {
-    internal: Rc<RefCell<VizRunnerInternal<'a>>>
+pub struct VizRunner<'a, B: Backend<'a> = Context<'a>> {
+    internal: Rc<RefCell<VizRunnerInternal<'a, B>>>
 }
 
 
 impl<'a> VizRunner<'a> {
     /// Create a new visuaizer
     pub fn new(mainloop: &'a PulseAudioMainloop) ->  VizRunner<'a> {
+        VizRunner::new_with_options(mainloop, VizOptions::default()).unwrap()
+    }
+
+    /// Create a new visualizer for the default server with the given options.
+    /// Fails if the FFT backend can't handle options.fft_size, before the
+    /// terminal is taken over.
+    pub fn new_with_options(mainloop: &'a PulseAudioMainloop, options: VizOptions) -> Result<VizRunner<'a>, FftError> {
+        let context = mainloop.create_context("rs_client");
+        let runner = VizRunner::with_options(context, None, options)?;
+        runner.add_renderer(Box::new(create_visualizer(&options)));
+        Ok(runner)
+    }
+}
+
+
+impl<'a, B: Backend<'a>> VizRunner<'a, B> {
+    /// Create a new visualizer on top of any PulseAudio backend. Frames are
+    /// computed but not shown until renderers are added; see add_renderer.
+    pub fn with_backend(backend: B) -> VizRunner<'a, B> {
+        VizRunner::with_server(backend, None)
+    }
+
+    /// Create a new visualizer which connects to a specific server, such as
+    /// "unix:/path/to/native". If server is None, uses the default server.
+    pub fn with_server(backend: B, server: Option<&str>) -> VizRunner<'a, B> {
+        VizRunner::with_options(backend, server, VizOptions::default()).unwrap()
+    }
+
+    /// Create a new visualizer with every setting spelled out. Fails if the
+    /// FFT backend can't handle options.fft_size. The options for drawing are
+    /// only used by create_visualizer.
+    pub fn with_options(backend: B, server: Option<&str>, options: VizOptions) -> Result<VizRunner<'a, B>, FftError> {
+        let fft = create_fft(&options)?;
+        Ok(VizRunner::from_parts(backend, server, fft, &options))
+    }
+
+    /// Assemble a runner and start connecting
+    fn from_parts(backend: B, server: Option<&str>, fft: AudioFft, options: &VizOptions) -> VizRunner<'a, B> {
+        let mut gain = GainControl::new(options.gain);
+        gain.set_floor(options.floor);
+        let bands = BandMapper::new(options.bands, options.band_count, DEFAULT_SAMPLE_SPEC.rate as f64,
+                                    options.fft_size);
         let vzr = VizRunner {
-            internal: Rc::new(RefCell::new(VizRunnerInternal::new(mainloop)))
+            internal: Rc::new(RefCell::new(VizRunnerInternal::new(backend, server, fft, gain,
+                                                                  Smoother::new(options.smoothing), bands)))
         };
         {
             let clone = vzr.clone();
@@ -38,28 +214,72 @@ impl<'a> VizRunner<'a> {
         }
         vzr
     }
+
+    /// Get the name of the monitor source currently being visualized
+    pub fn get_monitor_name(&self) -> Option<String> {
+        self.internal.borrow().monitor_name.clone()
+    }
+
+    /// The number of FFT frames computed so far
+    pub fn get_frame_count(&self) -> usize {
+        self.internal.borrow().frame_count
+    }
+
+    /// Hand every frame from now on to a renderer as well. Renderers get
+    /// each frame in the order they were added.
+    pub fn add_renderer(&self, renderer: Box<dyn Renderer>) {
+        self.internal.borrow_mut().renderers.push(renderer);
+    }
 }
 
 
-struct VizRunnerInternal<'a> {
-    context: Context<'a>,
+struct VizRunnerInternal<'a, B: Backend<'a>> {
+    context: B,
     fft: AudioFft,
-    viz: visualizer::Visualizer,
-    external: Option<VizRunner<'a>>,
-    stream: Option<PulseAudioStream<'a>>,
+    gain: GainControl,
+    smoother: Smoother,
+    scope: Oscilloscope,
+    /// Maps the smoothed levels onto the bands frames carry
+    bands: BandMapper,
+    /// Buffers for each frame, kept so they don't have to be allocated
+    /// every hop: the levels going into the smoother, the raw FFT output,
+    /// amplitudes for band mapping, and the levels and peaks of the bands
+    levels: Vec<f64>,
+    raw: Vec<f64>,
+    amplitudes: Vec<f64>,
+    band_levels: Vec<f64>,
+    band_peaks: Vec<f64>,
+    renderers: Vec<Box<dyn Renderer>>,
+    external: Option<VizRunner<'a, B>>,
+    stream: Option<B::Stream>,
+    monitor_name: Option<String>,
+    server: Option<String>,
+    frame_count: usize,
 }
 
 
-impl<'a> VizRunnerInternal<'a> {
+impl<'a, B: Backend<'a>> VizRunnerInternal<'a, B> {
     /// Create a new instance of the VizRunnerInternal struct
-    fn new(mainloop: &'a PulseAudioMainloop) -> VizRunnerInternal<'a> {
-        let context = mainloop.create_context("rs_client");
+    fn new(context: B, server: Option<&str>, fft: AudioFft, gain: GainControl,
+           smoother: Smoother, bands: BandMapper) -> VizRunnerInternal<'a, B> {
         VizRunnerInternal {
             context: context,
-            fft: AudioFft::new(1024, 2),
-            viz: visualizer::Visualizer::new(),
+            fft: fft,
+            gain: gain,
+            smoother: smoother,
+            scope: Oscilloscope::new(),
+            bands: bands,
+            levels: Vec::new(),
+            raw: Vec::new(),
+            amplitudes: Vec::new(),
+            band_levels: Vec::new(),
+            band_peaks: Vec::new(),
+            renderers: Vec::new(),
             external: None,
-            stream: None
+            stream: None,
+            monitor_name: None,
+            server: server.map(|server| server.to_string()),
+            frame_count: 0,
         }
     }
 
@@ -67,15 +287,10 @@ impl<'a> VizRunnerInternal<'a> {
     fn subscribe_to_sink_changes(&mut self) {
         let external = self.external.clone().unwrap();
 
-        self.context.set_event_callback(move |_, event, _| {
-            let facility = event & (pa_subscription_event_type::FACILITY_MASK as c_int);
-            let ev_type = event & (pa_subscription_event_type::TYPE_MASK as c_int);
-
-            if facility == pa_subscription_event_type::SERVER as c_int {
-                if ev_type == pa_subscription_event_type::CHANGE as c_int {
-                    let mut internal = external.internal.borrow_mut();
-                    internal.update_sink();
-                }
+        self.context.set_event_callback(move |_, event| {
+            if event.facility == EventFacility::Server && event.kind == EventKind::Change {
+                let mut internal = external.internal.borrow_mut();
+                internal.update_sink();
             }
         });
 
@@ -98,7 +313,8 @@ impl<'a> VizRunnerInternal<'a> {
             }
         });
 
-        self.context.connect(None, pa_context_flags::NOAUTOSPAWN);
+        let server = self.server.clone();
+        self.context.connect(server.as_ref().map(|s| &s[..]), pa_context_flags::NOAUTOSPAWN);
     }
 
     /// Callled when the context is ready
@@ -111,13 +327,17 @@ impl<'a> VizRunnerInternal<'a> {
     fn update_sink(&mut self) {
         let external = self.external.clone().unwrap();
         self.context.get_server_info(move |_, info| {
+            let sink_name = match info.default_sink_name {
+                Some(name) => name,
+                None => return,
+            };
             let internal = external.internal.borrow();
             let external = external.clone();
-            internal.context.get_sink_info_by_name(info.get_default_sink_name(), move |_, info| {
-                match info {
-                    Some(info) => {
+            internal.context.get_sink_info_by_name(&sink_name, move |_, info| {
+                match info.and_then(|info| info.monitor_source_name) {
+                    Some(monitor_name) => {
                         let mut internal = external.internal.borrow_mut();
-                        internal.set_sink(info.get_monitor_source_name());
+                        internal.set_sink(&monitor_name);
                     },
                     None => {}
                 }
@@ -132,6 +352,7 @@ impl<'a> VizRunnerInternal<'a> {
             None => {}
         }
         self.stream = None;
+        self.monitor_name = None;
 
         let mut stream = self.context.create_stream("rs_client", &DEFAULT_SAMPLE_SPEC, None);
         let external = self.external.clone().unwrap();
@@ -142,13 +363,69 @@ impl<'a> VizRunnerInternal<'a> {
         });
         stream.connect_record(Some(monitor_name), None, None).unwrap();
         self.stream = Some(stream);
+        self.monitor_name = Some(monitor_name.to_string());
     }
 
     /// Called whenever the FFT has enough data to run a frame of the visualizer
     fn on_fft_frame_ready(&mut self) {
         self.fft.execute();
         self.fft.compute_output();
-        self.viz.render_frame(self.fft.get_output()).unwrap();
+        let index = self.frame_count;
+        self.frame_count += 1;
+        // Frames are hop_size samples of audio apart, however bursty the
+        // stream is
+        let elapsed = self.fft.get_hop_size() as f64 / DEFAULT_SAMPLE_SPEC.rate as f64;
+        self.gain.update(self.fft.get_output(), elapsed);
+        // Smooth the combined levels and then each channel's, one after
+        // another
+        let bins = self.fft.get_output().len();
+        let channel_count = self.fft.get_channel_count();
+        self.levels.clear();
+        if self.gain.is_open() {
+            self.levels.extend(self.fft.get_output().iter().map(|level| level.to_f64()));
+            for channel in 0..channel_count {
+                self.levels.extend(self.fft.get_channel_output(channel).iter().map(|level| level.to_f64()));
+            }
+        } else {
+            // Silence: let the bars fall flat rather than show the noise
+            let floor = self.fft.get_floor();
+            self.levels.extend((0..bins * (channel_count + 1)).map(|_| floor));
+        }
+        self.smoother.update(&self.levels, elapsed);
+
+        if self.renderers.is_empty() {
+            return;
+        }
+        self.scope.update(&self.fft);
+        self.raw.clear();
+        self.raw.extend(self.fft.get_output().iter().map(|level| level.to_f64()));
+        self.band_levels.clear();
+        map_bands(&self.bands, self.smoother.get_levels(), bins, &mut self.amplitudes, &mut self.band_levels);
+        self.band_peaks.clear();
+        if let Some(peaks) = self.smoother.get_peaks() {
+            map_bands(&self.bands, peaks, bins, &mut self.amplitudes, &mut self.band_peaks);
+        }
+
+        let rate = DEFAULT_SAMPLE_SPEC.rate as f64;
+        let band_peaks = &self.band_peaks[..];
+        let frame = SpectrumFrame {
+            index: index,
+            time: (self.fft.get_fft_size() + index * self.fft.get_hop_size()) as f64 / rate,
+            sample_rate: rate,
+            fft_size: self.fft.get_fft_size(),
+            range: self.gain.get_range(),
+            bands: self.bands.get_bands(),
+            channel_count: channel_count,
+            raw: &self.raw,
+            scope: &self.scope,
+            levels: self.smoother.get_levels(),
+            peaks: self.smoother.get_peaks(),
+            band_levels: &self.band_levels,
+            band_peaks: self.smoother.get_peaks().map(|_| band_peaks),
+        };
+        for renderer in self.renderers.iter_mut() {
+            renderer.render(&frame);
+        }
     }
 
     /// Handles a stale stream and returns true if the stream was stale
@@ -157,26 +434,20 @@ impl<'a> VizRunnerInternal<'a> {
     /// stream to reach the ready state only to disconnect it, this will get
     /// called the first time the stream has data available and disconnect it
     /// then.
-    fn handle_stale_stream(&mut self, stream: &mut PulseAudioStream) -> bool {
+    fn handle_stale_stream(&mut self, stream: &mut B::Stream) -> bool {
         match self.stream {
-            Some(ref s) => {
-                if s.get_raw_ptr() != stream.get_raw_ptr() {
-                    // disconnect frequently fails if the stream is in the wrong state,
-                    // so if we got data for a stale stream, try disconnecting it again
-                    stream.disconnect();
-                    true
-                } else {
-                    false
-                }
+            Some(ref s) if !s.same_stream(stream) => {
+                // disconnect frequently fails if the stream is in the wrong state,
+                // so if we got data for a stale stream, try disconnecting it again
+                stream.disconnect();
+                true
             },
-            None => {
-                false
-            }
+            _ => false
         }
     }
 
     /// Handle the callback from PulseAudio telling us that stream data is ready
-    fn stream_read_callback(&mut self, mut stream: PulseAudioStream, _: size_t) {
+    fn stream_read_callback(&mut self, mut stream: B::Stream, _: size_t) {
         if self.handle_stale_stream(&mut stream) {
             return
         }
@@ -184,11 +455,12 @@ impl<'a> VizRunnerInternal<'a> {
         match stream.peek() {
             Ok(data) => {
                 let mut fed_count: usize = 0;
-                while fed_count < data.len() {
-                    fed_count += self.fft.feed_u8_data(data);
-                    if fed_count < data.len() {
-                        self.on_fft_frame_ready();
+                loop {
+                    fed_count += self.fft.feed_u8_data(&data[fed_count..]);
+                    if !self.fft.is_ready() {
+                        break;
                     }
+                    self.on_fft_frame_ready();
                 }
             },
             Err(_) => return
@@ -197,3 +469,132 @@ impl<'a> VizRunnerInternal<'a> {
         stream.drop_fragment().unwrap();
     }
 }
+
+
+#[cfg(test)]
+use pulse::mock::MockBackend;
+
+
+/// Connect a headless runner to a mock server with two sinks
+#[cfg(test)]
+fn connect_mock_runner<'a>() -> (MockBackend<'a>, VizRunner<'a, MockBackend<'a>>) {
+    let backend = MockBackend::new();
+    backend.add_sink("speakers");
+    backend.add_sink("headphones");
+    let runner = VizRunner::with_backend(backend.clone());
+    backend.run_pending();
+    (backend, runner)
+}
+
+
+#[test]
+fn test_runner_records_default_sink_monitor() {
+    let (backend, runner) = connect_mock_runner();
+
+    assert_eq!(runner.get_monitor_name(), Some("speakers.monitor".to_string()));
+    let streams = backend.streams();
+    assert_eq!(streams.len(), 1);
+    assert!(streams[0].is_connected());
+    assert_eq!(streams[0].source_name(), Some("speakers.monitor".to_string()));
+}
+
+
+#[test]
+fn test_runner_follows_default_sink_change() {
+    let (backend, runner) = connect_mock_runner();
+
+    backend.set_default_sink("headphones");
+    backend.run_pending();
+
+    assert_eq!(runner.get_monitor_name(), Some("headphones.monitor".to_string()));
+    let streams = backend.streams();
+    assert_eq!(streams.len(), 2);
+    assert!(!streams[0].is_connected());
+    assert!(streams[1].is_connected());
+    assert_eq!(streams[1].source_name(), Some("headphones.monitor".to_string()));
+}
+
+
+#[test]
+fn test_runner_disconnects_stale_stream() {
+    let (backend, _runner) = connect_mock_runner();
+
+    // The first disconnect fails, so the old stream keeps delivering data
+    backend.set_fail_disconnects(true);
+    backend.set_default_sink("headphones");
+    backend.run_pending();
+    let streams = backend.streams();
+    assert_eq!(streams[0].disconnect_count(), 1);
+    assert!(streams[0].is_connected());
+
+    backend.set_fail_disconnects(false);
+    assert_eq!(backend.deliver_samples("speakers.monitor", &[0; 64]), 1);
+    backend.run_pending();
+    assert_eq!(streams[0].disconnect_count(), 2);
+    assert!(!streams[0].is_connected());
+    assert_eq!(streams[1].disconnect_count(), 0);
+}
+
+
+#[test]
+fn test_runner_consumes_samples() {
+    let (backend, runner) = connect_mock_runner();
+
+    // Two and a half FFT frames of stereo S16LE silence
+    let data = vec![0u8; 1024 * 2 * 2 * 5 / 2];
+    assert_eq!(backend.deliver_samples("speakers.monitor", &data), 1);
+    backend.run_pending();
+    assert_eq!(backend.streams()[0].pending_fragments(), 0);
+
+    // With the default 50% overlap, a frame after 1024 samples and then
+    // every 512
+    assert_eq!(runner.get_frame_count(), 4);
+}
+
+
+#[test]
+fn test_runner_feeds_renderers() {
+    use renderer::{NullRenderer, RecordingRenderer};
+    use std::f64::consts::PI;
+
+    let (backend, runner) = connect_mock_runner();
+    let recorder = RecordingRenderer::new();
+    let frames = recorder.get_frames();
+    runner.add_renderer(Box::new(NullRenderer));
+    runner.add_renderer(Box::new(recorder));
+
+    // Two and a half FFT frames of a tone 32 bins up, louder on the left
+    let mut data = Vec::new();
+    for i in 0..1024 * 5 / 2 {
+        let sine = (2.0 * PI * 32.0 * i as f64 / 1024.0).sin();
+        for &amplitude in [16384.0, 4096.0].iter() {
+            let sample = (amplitude * sine).round() as i16;
+            data.push(sample as u8);
+            data.push((sample >> 8) as u8);
+        }
+    }
+    backend.deliver_samples("speakers.monitor", &data);
+    backend.run_pending();
+
+    let frames = frames.borrow();
+    assert_eq!(frames.len(), 4);
+    for (index, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.index, index);
+        assert!((frame.time - (1024 + 512 * index) as f64 / 44100.0).abs() < 1e-9);
+        assert_eq!(frame.range, (-80.0, 0.0));
+        assert_eq!(frame.levels.len(), 512);
+        assert_eq!(frame.channel_levels.len(), 2);
+        assert_eq!(frame.band_levels.len(), 32);
+    }
+
+    // The channels are -6 and -18dBFS, and the mix takes the louder
+    let last = &frames[3];
+    let loudest = (0..512).fold(0, |max, bin| if last.levels[bin] > last.levels[max] { bin } else { max });
+    assert_eq!(loudest, 32);
+    assert!((last.channel_levels[0][32] + 6.0).abs() < 0.5, "{}", last.channel_levels[0][32]);
+    assert!((last.channel_levels[1][32] + 18.0).abs() < 0.5, "{}", last.channel_levels[1][32]);
+    assert_eq!(last.levels[32], last.channel_levels[0][32]);
+    // The band with the tone in it takes its level
+    let loudest = last.band_levels.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    assert!((loudest + 6.0).abs() < 0.5, "{}", loudest);
+}
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+use bands::Aggregation;
+use visualizer::scale_fft_output;
+
+
+/// Shades for a cell of the waterfall, from silent to loud
+const SHADE_CHARS: [char; 5] = [' ', '\u{2591}', '\u{2592}', '\u{2593}', '\u{2588}'];
+
+
+/// Shades for terminals without Unicode
+const ASCII_SHADE_CHARS: [char; 5] = [' ', '.', ':', '+', '#'];
+
+
+/// Which way time runs across the screen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Orientation {
+    /// Newest frame at the top, scrolling down, with frequencies across
+    Vertical,
+    /// Newest frame on the right, scrolling left, with frequencies going up
+    Horizontal,
+}
+
+
+/// Parses "vertical" or "horizontal"
+impl FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Orientation, String> {
+        match name {
+            "vertical" => Ok(Orientation::Vertical),
+            "horizontal" => Ok(Orientation::Horizontal),
+            _ => Err(format!("unknown orientation \"{}\"", name)),
+        }
+    }
+}
+
+
+/// How the waterfall is drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WaterfallSettings {
+    /// The levels in dBFS drawn as blank and as solid. None follows the gain
+    /// control, like the bars.
+    pub range: Option<(f64, f64)>,
+    /// How many frames the screen covers. Frames are combined or stretched
+    /// to fit. 0 gives each frame its own line.
+    pub history: usize,
+    pub orientation: Orientation,
+}
+
+
+impl Default for WaterfallSettings {
+    fn default() -> WaterfallSettings {
+        WaterfallSettings {
+            range: None,
+            history: 0,
+            orientation: Orientation::Vertical,
+        }
+    }
+}
+
+
+/// The shade for a cell, filled by fraction, from 0 to 1
+pub fn shade_char(fraction: f64, unicode: bool) -> char {
+    let shades = if unicode { &SHADE_CHARS } else { &ASCII_SHADE_CHARS };
+    let index = (fraction.clamp(0.0, 1.0) * (shades.len() - 1) as f64).round() as usize;
+    shades[index]
+}
+
+
+/// A history of band levels, one line per frame, for a scrolling spectrogram
+pub struct Waterfall {
+    settings: WaterfallSettings,
+    /// The levels of each band in dBFS, oldest frame first
+    lines: VecDeque<Vec<f64>>,
+}
+
+
+impl Waterfall {
+    pub fn new(settings: WaterfallSettings) -> Waterfall {
+        Waterfall {
+            settings: settings,
+            lines: VecDeque::new(),
+        }
+    }
+
+    pub fn get_settings(&self) -> WaterfallSettings {
+        self.settings
+    }
+
+    /// Add the newest frame of band levels, keeping the last capacity frames.
+    /// A frame with a different number of bands, as when the screen is
+    /// resized, starts the history over.
+    pub fn push(&mut self, levels: Vec<f64>, capacity: usize) {
+        if self.lines.back().map_or(false, |line| line.len() != levels.len()) {
+            self.lines.clear();
+        }
+        self.lines.push_back(levels);
+        while self.lines.len() > capacity {
+            self.lines.pop_front();
+        }
+    }
+
+    /// The last capacity frames fit to count lines, oldest first. Frames from
+    /// before the first are filled in with the empty level. Lines covering
+    /// several frames get the loudest level of each band.
+    pub fn get_lines(&self, count: usize, capacity: usize, empty: f64) -> Vec<Vec<f64>> {
+        let bands = self.lines.back().map_or(0, |line| line.len());
+        let missing = capacity.saturating_sub(self.lines.len());
+        let skipped = self.lines.len().saturating_sub(capacity);
+        let frames: Vec<&[f64]> = self.lines.iter().skip(skipped).map(|line| &line[..]).collect();
+        if capacity == count {
+            let blank = vec![empty; bands];
+            return (0..missing).map(|_| blank.clone())
+                .chain(frames.iter().map(|line| line.to_vec()))
+                .collect();
+        }
+
+        // Resample each band's history to the number of lines
+        let mut lines = vec![Vec::with_capacity(bands); count];
+        for band in 0..bands {
+            let history: Vec<f64> = (0..missing).map(|_| empty)
+                .chain(frames.iter().map(|line| line[band]))
+                .collect();
+            for (line, level) in lines.iter_mut().zip(scale_fft_output(&history, count, Aggregation::Max)) {
+                line.push(level);
+            }
+        }
+        lines
+    }
+}
+
+
+#[test]
+fn test_history() {
+    let mut waterfall = Waterfall::new(WaterfallSettings::default());
+    for frame in 0..5 {
+        waterfall.push(vec![frame as f64, -(frame as f64)], 3);
+    }
+    // The last three frames, padded out to four lines
+    assert_eq!(waterfall.get_lines(4, 4, -100.0),
+               vec![vec![-100.0, -100.0], vec![2.0, -2.0], vec![3.0, -3.0], vec![4.0, -4.0]]);
+    // Squeezed into fewer lines, the loudest frame wins
+    assert_eq!(waterfall.get_lines(2, 4, -100.0), vec![vec![2.0, -2.0], vec![4.0, -3.0]]);
+    // A shorter screen shows the newest frames
+    assert_eq!(waterfall.get_lines(2, 2, -100.0), vec![vec![3.0, -3.0], vec![4.0, -4.0]]);
+
+    // Resizing starts over
+    waterfall.push(vec![7.0; 3], 3);
+    assert_eq!(waterfall.get_lines(1, 1, -100.0), vec![vec![7.0; 3]]);
+}
+
+
+#[test]
+fn test_shades() {
+    let shades: Vec<char> = [-0.5, 0.0, 0.3, 0.5, 0.9, 1.0].iter().map(|&f| shade_char(f, false)).collect();
+    assert_eq!(shades, vec![' ', ' ', '.', ':', '#', '#']);
+    assert_eq!(shade_char(0.75, true), '\u{2593}');
+}
@@ -0,0 +1,269 @@
+//! Integration tests against a private, headless PulseAudio server.
+//!
+//! Each test starts its own `pulseaudio --daemonize=no` listening on a socket
+//! in a temporary directory, with null sinks instead of sound hardware. If the
+//! pulseaudio binary isn't installed, each test prints a SKIPPED line to stderr
+//! and returns without checking anything.
+
+extern crate rusty_bars;
+
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Child, Command, Stdio};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rusty_bars::fftw::audio::AudioFft;
+use rusty_bars::fftw::float::FftFloat;
+use rusty_bars::pulse::{Context, PulseAudioMainloop};
+use rusty_bars::pulse::types::*;
+use rusty_bars::viz_runner::VizRunner;
+
+
+const RATE: u32 = 44100;
+const CHANNELS: u8 = 2;
+const FFT_SIZE: usize = 1024;
+
+/// How long to wait on the server before failing a test
+const TIMEOUT_SECS: u64 = 10;
+
+
+/// A pulseaudio daemon which is killed when dropped
+struct TestServer {
+    child: Child,
+    dir: PathBuf,
+}
+
+
+impl TestServer {
+    /// Start a server with the given null sinks. The first sink is the
+    /// default. Returns None if pulseaudio isn't installed.
+    fn start(name: &str, sinks: &[&str]) -> Option<TestServer> {
+        let dir = env::temp_dir().join(format!("rusty_bars_{}_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut cmd = Command::new("pulseaudio");
+        cmd.arg("-n")
+            .arg("--daemonize=no")
+            .arg("--exit-idle-time=-1")
+            .arg("--use-pid-file=no")
+            .arg("--disable-shm=yes")
+            .arg("-L").arg(format!("module-native-protocol-unix auth-anonymous=1 socket={}",
+                                   dir.join("native").display()));
+        for sink in sinks.iter() {
+            cmd.arg("-L").arg(format!("module-null-sink sink_name={} rate={} channels={}",
+                                      sink, RATE, CHANNELS));
+        }
+
+        // Keep the daemon away from the user's config, runtime dir and cookie
+        cmd.env("HOME", &dir)
+            .env("XDG_CONFIG_HOME", &dir)
+            .env("XDG_RUNTIME_DIR", &dir)
+            .env("PULSE_RUNTIME_PATH", &dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                eprintln!("SKIPPED {}: couldn't run pulseaudio: {}", name, err);
+                let _ = fs::remove_dir_all(&dir);
+                return None;
+            }
+        };
+
+        let mut server = TestServer { child: child, dir: dir };
+        let deadline = Instant::now() + Duration::from_secs(TIMEOUT_SECS);
+        while !server.dir.join("native").exists() {
+            if let Some(status) = server.child.try_wait().unwrap() {
+                panic!("pulseaudio exited before creating its socket: {}", status);
+            }
+            assert!(Instant::now() < deadline, "pulseaudio didn't create its socket");
+            thread::sleep(Duration::from_millis(50));
+        }
+        server.pactl(&["set-default-sink", sinks[0]]);
+        Some(server)
+    }
+
+    /// The server string to pass to pa_context_connect
+    fn address(&self) -> String {
+        format!("unix:{}", self.dir.join("native").display())
+    }
+
+    /// Run pactl against this server
+    fn pactl(&self, args: &[&str]) {
+        let status = Command::new("pactl")
+            .arg(format!("--server={}", self.address()))
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "pactl {:?} failed", args);
+    }
+}
+
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+
+/// Iterate the mainloop until done returns true, or panic after a timeout
+fn run_until<F: FnMut() -> bool>(mainloop: &PulseAudioMainloop, what: &str, mut done: F) {
+    let deadline = Instant::now() + Duration::from_secs(TIMEOUT_SECS);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        mainloop.iterate(false).unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+
+/// Connect a context to the server and wait for it to be ready
+fn connect<'a>(mainloop: &'a PulseAudioMainloop, server: &TestServer) -> Context<'a> {
+    let context = mainloop.create_context("rusty_bars_test");
+    let ready = Rc::new(Cell::new(false));
+    let ready_cb = ready.clone();
+    context.set_state_callback(move |_, state| {
+        match state {
+            pa_context_state::READY => ready_cb.set(true),
+            pa_context_state::FAILED | pa_context_state::TERMINATED => panic!("context failed"),
+            _ => {}
+        }
+    });
+    context.connect(Some(&server.address()), pa_context_flags::NOAUTOSPAWN);
+    run_until(mainloop, "context to be ready", || ready.get());
+    context
+}
+
+
+/// Generates an interleaved S16LE sine wave in the same phase on every channel
+struct SineGenerator {
+    freq: f64,
+    amplitude: f64,
+    frame: u64,
+}
+
+
+impl SineGenerator {
+    /// Get nbytes worth of samples, continuing from the last call
+    fn generate(&mut self, nbytes: usize) -> Vec<u8> {
+        let frame_bytes = 2 * CHANNELS as usize;
+        let mut data = Vec::with_capacity(nbytes);
+        for _ in 0..(nbytes / frame_bytes) {
+            let t = self.frame as f64 / RATE as f64;
+            let sample = (self.amplitude * (2.0 * PI * self.freq * t).sin()) as i16;
+            for _ in 0..CHANNELS {
+                data.push(sample as u8);
+                data.push((sample >> 8) as u8);
+            }
+            self.frame += 1;
+        }
+        data
+    }
+}
+
+
+#[test]
+fn test_fft_peak_matches_tone() {
+    let server = match TestServer::start("tone", &["tone_sink"]) {
+        Some(server) => server,
+        None => return
+    };
+
+    let spec = pa_sample_spec {
+        format: pa_sample_format::PA_SAMPLE_S16LE,
+        rate: RATE,
+        channels: CHANNELS,
+    };
+
+    let mainloop = PulseAudioMainloop::new();
+    let mut context = connect(&mainloop, &server);
+
+    // Pick a frequency exactly on a bin's frequency so its energy peaks there
+    // and doesn't spread evenly across two neighbouring bins
+    let expected_bin = 40;
+    let freq = expected_bin as f64 * RATE as f64 / FFT_SIZE as f64;
+
+    let mut playback = context.create_stream("rusty_bars_test_tone", &spec, None);
+    let mut generator = SineGenerator { freq: freq, amplitude: 16000.0, frame: 0 };
+    playback.set_write_callback(move |mut stream, nbytes| {
+        let data = generator.generate(nbytes as usize);
+        stream.write(&data).unwrap();
+    });
+    playback.connect_playback(Some("tone_sink"), None, None).unwrap();
+
+    let peaks: Rc<RefCell<Vec<usize>>> = Rc::new(RefCell::new(Vec::new()));
+    let peaks_cb = peaks.clone();
+    let mut fft: AudioFft = AudioFft::new(FFT_SIZE, CHANNELS as usize).unwrap();
+    let mut record = context.create_stream("rusty_bars_test_record", &spec, None);
+    record.set_read_callback(move |mut stream, _| {
+        if let Ok(data) = stream.peek() {
+            let mut fed_count = 0;
+            loop {
+                fed_count += fft.feed_u8_data(&data[fed_count..]);
+                if !fft.is_ready() {
+                    break;
+                }
+                fft.execute();
+                fft.compute_output();
+                let output = fft.get_output();
+                let mut peak = 0;
+                for (index, &value) in output.iter().enumerate() {
+                    if value > output[peak] {
+                        peak = index;
+                    }
+                }
+                // Skip the silence before playback starts. The tone is about
+                // -6dBFS.
+                if output[peak].to_f64() > -20.0 {
+                    peaks_cb.borrow_mut().push(peak);
+                }
+            }
+        }
+        let _ = stream.drop_fragment();
+    });
+    record.connect_record(Some("tone_sink.monitor"), None, None).unwrap();
+
+    run_until(&mainloop, "FFT frames of the tone", || peaks.borrow().len() >= 10);
+
+    // The first frame may straddle the start of the tone
+    for &peak in peaks.borrow()[1..].iter() {
+        assert_eq!(peak, expected_bin);
+    }
+}
+
+
+#[test]
+fn test_runner_follows_default_sink() {
+    let server = match TestServer::start("follow", &["sink_a", "sink_b"]) {
+        Some(server) => server,
+        None => return
+    };
+
+    let mainloop = PulseAudioMainloop::new();
+    let context = mainloop.create_context("rusty_bars_test");
+    let runner = VizRunner::with_server(context, Some(&server.address()));
+
+    run_until(&mainloop, "the runner to pick the default sink", || {
+        runner.get_monitor_name() == Some("sink_a.monitor".to_string())
+    });
+
+    server.pactl(&["set-default-sink", "sink_b"]);
+    run_until(&mainloop, "the runner to follow the default sink", || {
+        runner.get_monitor_name() == Some("sink_b.monitor".to_string())
+    });
+
+    server.pactl(&["set-default-sink", "sink_a"]);
+    run_until(&mainloop, "the runner to switch back", || {
+        runner.get_monitor_name() == Some("sink_a.monitor".to_string())
+    });
+}